@@ -0,0 +1,236 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use interpolatable::Interpolatable;
+use interpolatable::Interpolator;
+
+use crate::color::{to_byte, Color};
+use crate::color_parsing::{parse_color, ColorParseError};
+use crate::colorizer::BlendMode;
+use crate::interpolatable;
+
+/// [Color] with opacity, stored with premultiplied alpha.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct AlphaColor {
+    /// Red component multiplied by alpha
+    red: f32,
+    /// Green component multiplied by alpha
+    green: f32,
+    /// Blue component multiplied by alpha
+    blue: f32,
+    /// Opacity: 0.0 - 1.0
+    alpha: f32,
+}
+
+impl AlphaColor {
+    /// Color from components which are not yet multiplied by `alpha`.
+    pub fn new(red: f32, green: f32, blue: f32, alpha: f32) -> AlphaColor {
+        AlphaColor { red: red * alpha, green: green * alpha, blue: blue * alpha, alpha }
+    }
+
+    pub const fn premultiplied(red: f32, green: f32, blue: f32, alpha: f32) -> AlphaColor {
+        AlphaColor { red, green, blue, alpha }
+    }
+
+    pub fn with_alpha(color: Color, alpha: f32) -> AlphaColor {
+        AlphaColor::new(color.red(), color.green(), color.blue(), alpha)
+    }
+
+    pub fn alpha(&self) -> f32 {
+        self.alpha
+    }
+
+    /// Color without opacity, black if fully transparent.
+    pub fn color(&self) -> Color {
+        if self.alpha == 0f32 {
+            Color::BLACK
+        } else {
+            Color::new(self.red / self.alpha, self.green / self.alpha, self.blue / self.alpha)
+        }
+    }
+
+    /// Color as seen on a black background.
+    pub fn flatten(&self) -> Color {
+        Color::new(self.red, self.green, self.blue)
+    }
+
+    /// Mean of all given colors, or transparent if there are none.
+    pub fn average(colors: &[AlphaColor]) -> AlphaColor {
+        if colors.is_empty() {
+            return AlphaColor::TRANSPARENT;
+        }
+        let count = colors.len() as f32;
+        let sum = colors.iter().fold(AlphaColor::TRANSPARENT, |sum, color| sum.map(color, |a, b| a + b));
+        sum.map(&sum, |value, _| value / count)
+    }
+
+    fn map(&self, other: &AlphaColor, operation: impl Fn(f32, f32) -> f32) -> AlphaColor {
+        AlphaColor {
+            red: operation(self.red, other.red),
+            green: operation(self.green, other.green),
+            blue: operation(self.blue, other.blue),
+            alpha: operation(self.alpha, other.alpha),
+        }
+    }
+
+    fn scale(&self, factor: f32) -> AlphaColor {
+        self.map(self, |value, _| value * factor)
+    }
+
+    /// Porter-Duff source over: this color in front of `below`.
+    pub fn over(&self, below: &AlphaColor) -> AlphaColor {
+        self.map(&below.scale(1f32 - self.alpha), |a, b| a + b)
+    }
+
+    /// Porter-Duff source in: the part of this color covered by `mask`.
+    pub fn inside(&self, mask: &AlphaColor) -> AlphaColor {
+        self.scale(mask.alpha)
+    }
+
+    /// Porter-Duff source out: the part of this color not covered by `mask`.
+    pub fn outside(&self, mask: &AlphaColor) -> AlphaColor {
+        self.scale(1f32 - mask.alpha)
+    }
+
+    /// Porter-Duff source atop: this color in front of `below`, only where `below` is.
+    pub fn atop(&self, below: &AlphaColor) -> AlphaColor {
+        let color = self.scale(below.alpha).map(&below.scale(1f32 - self.alpha), |a, b| a + b);
+        AlphaColor { alpha: below.alpha, ..color }
+    }
+
+    /// This color in front of `below`, where both overlap combined by `mode`.
+    pub fn blend(&self, below: &AlphaColor, mode: BlendMode) -> AlphaColor {
+        let blended = mode.blend(below.color(), self.color());
+        let overlap = self.alpha * below.alpha;
+        let component = |source: f32, backdrop: f32, blended: f32| {
+            source * (1f32 - below.alpha) + backdrop * (1f32 - self.alpha) + blended * overlap
+        };
+        AlphaColor {
+            red: component(self.red, below.red, blended.red()),
+            green: component(self.green, below.green, blended.green()),
+            blue: component(self.blue, below.blue, blended.blue()),
+            alpha: self.alpha + below.alpha - overlap,
+        }
+    }
+
+    pub const TRANSPARENT: AlphaColor = AlphaColor {
+        red: 0f32,
+        green: 0f32,
+        blue: 0f32,
+        alpha: 0f32,
+    };
+}
+
+impl From<Color> for AlphaColor {
+    fn from(color: Color) -> Self {
+        AlphaColor::with_alpha(color, 1f32)
+    }
+}
+
+/// Interpolates the premultiplied components, so transparent colors do not bleed into opaque ones.
+impl Interpolatable<AlphaColor> for AlphaColor {
+    type Output = AlphaColor;
+
+    fn interpolate(&self, interpolator: &Interpolator, other: &AlphaColor) -> Self::Output {
+        let red = interpolator.interpolate(&self.red, &other.red);
+        let green = interpolator.interpolate(&self.green, &other.green);
+        let blue = interpolator.interpolate(&self.blue, &other.blue);
+        let alpha = interpolator.interpolate(&self.alpha, &other.alpha);
+        AlphaColor { red, green, blue, alpha }
+    }
+}
+
+/// Image pixels use straight alpha.
+impl From<AlphaColor> for image::Rgba<f32> {
+    fn from(color: AlphaColor) -> Self {
+        let straight = color.color();
+        image::Rgba([straight.red(), straight.green(), straight.blue(), color.alpha])
+    }
+}
+
+/// Parses sRGB encoded CSS colors like `#ff880080`, `transparent` or `rgb(255 136 0 / 50%)`.
+impl FromStr for AlphaColor {
+    type Err = ColorParseError;
+
+    fn from_str(color: &str) -> Result<Self, Self::Err> {
+        parse_color(color)
+    }
+}
+
+/// Formats as sRGB encoded hex notation, with alpha digits only if not opaque, e.g. `#ff8800` or `#ff880080`.
+impl Display for AlphaColor {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.color())?;
+        if self.alpha < 1f32 {
+            write!(f, "{:02x}", to_byte(self.alpha))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OPAQUE_BLUE: AlphaColor = AlphaColor::premultiplied(0f32, 0f32, 1f32, 1f32);
+
+    fn half_red() -> AlphaColor {
+        AlphaColor::new(1f32, 0f32, 0f32, 0.5f32)
+    }
+
+    #[test]
+    fn opaque_over_hides_below() {
+        let red = AlphaColor::from(Color::RED);
+        assert_eq!(red.over(&OPAQUE_BLUE), red);
+        assert_eq!(red.over(&AlphaColor::new(0f32, 0f32, 1f32, 0.5f32)), red);
+    }
+
+    #[test]
+    fn transparent_over_is_identity() {
+        assert_eq!(AlphaColor::TRANSPARENT.over(&OPAQUE_BLUE), OPAQUE_BLUE);
+        assert_eq!(AlphaColor::TRANSPARENT.over(&half_red()), half_red());
+    }
+
+    #[test]
+    fn half_alpha_over() {
+        assert_eq!(half_red().over(&OPAQUE_BLUE), AlphaColor::premultiplied(0.5f32, 0f32, 0.5f32, 1f32));
+
+        let result = half_red().over(&AlphaColor::new(0f32, 0f32, 1f32, 0.5f32));
+        assert_eq!(result, AlphaColor::premultiplied(0.5f32, 0f32, 0.25f32, 0.75f32));
+        let color = result.color();
+        assert!((color.red() - 2f32 / 3f32).abs() < 1e-6 && (color.blue() - 1f32 / 3f32).abs() < 1e-6);
+    }
+
+    #[test]
+    fn masks() {
+        let mask = AlphaColor::new(0f32, 1f32, 0f32, 0.25f32);
+        assert_eq!(half_red().inside(&mask), AlphaColor::premultiplied(0.125f32, 0f32, 0f32, 0.125f32));
+        assert_eq!(half_red().outside(&mask), AlphaColor::premultiplied(0.375f32, 0f32, 0f32, 0.375f32));
+        assert_eq!(half_red().atop(&OPAQUE_BLUE), AlphaColor::premultiplied(0.5f32, 0f32, 0.5f32, 1f32));
+        assert_eq!(half_red().atop(&AlphaColor::TRANSPARENT), AlphaColor::TRANSPARENT);
+    }
+
+    #[test]
+    fn normal_blend_is_over() {
+        let below = AlphaColor::new(0.25f32, 0.5f32, 1f32, 0.5f32);
+        assert_eq!(half_red().blend(&below, BlendMode::NORMAL), half_red().over(&below));
+        assert_eq!(half_red().blend(&OPAQUE_BLUE, BlendMode::NORMAL), half_red().over(&OPAQUE_BLUE));
+    }
+
+    #[test]
+    fn blend_mixes_only_overlap() {
+        let layer = AlphaColor::new(1f32, 0.5f32, 0.5f32, 0.5f32);
+        let below = AlphaColor::from(Color::new(0.5f32, 0.5f32, 1f32));
+        // Half of the backdrop stays, the other half is the product of both colors.
+        let expected = AlphaColor::premultiplied(0.5f32, 0.375f32, 0.75f32, 1f32);
+        assert_eq!(layer.blend(&below, BlendMode::MULTIPLY), expected);
+        // Without a backdrop, the layer stays as it is.
+        assert_eq!(layer.blend(&AlphaColor::TRANSPARENT, BlendMode::MULTIPLY), layer);
+    }
+
+    #[test]
+    fn flatten_keeps_premultiplied_components() {
+        assert_eq!(AlphaColor::new(1f32, 0.5f32, 0f32, 0.5f32).flatten(), Color::new(0.5f32, 0.25f32, 0f32));
+        assert_eq!(AlphaColor::TRANSPARENT.flatten(), Color::BLACK);
+    }
+}
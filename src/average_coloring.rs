@@ -0,0 +1,147 @@
+use std::f64::consts::PI;
+
+use crate::complex::ComplexF64;
+
+/// Coloring accumulating a value over every orbit point, averaged at the end of the iteration.
+///
+/// All of them work best with a large bailout, e.g. `100.0` or more.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum AverageColoring {
+    /// Stripe average with the given stripe density, usually between `1.0` and `10.0`.
+    STRIPE(f64),
+    /// Triangle inequality average, assuming a formula of the form `z^2 + c`.
+    TRIANGLE,
+    /// Average of the angles between consecutive orbit steps.
+    CURVATURE,
+}
+
+/// Running state of an [AverageColoring] for a single orbit.
+pub(crate) struct AverageAccumulator {
+    coloring: AverageColoring,
+    c_norm: f64,
+    sum: f64,
+    last_term: f64,
+    count: u32,
+    previous: Option<ComplexF64>,
+    before_previous: Option<ComplexF64>,
+}
+
+impl AverageAccumulator {
+    pub(crate) fn new(coloring: AverageColoring, start: ComplexF64, c: ComplexF64) -> AverageAccumulator {
+        AverageAccumulator {
+            coloring,
+            c_norm: c.norm(),
+            sum: 0f64,
+            last_term: 0f64,
+            count: 0,
+            previous: Some(start),
+            before_previous: None,
+        }
+    }
+
+    pub(crate) fn add(&mut self, z: ComplexF64) {
+        if let Some(term) = self.term(z) {
+            self.sum += term;
+            self.last_term = term;
+            self.count += 1;
+        }
+        self.before_previous = self.previous;
+        self.previous = Some(z);
+    }
+
+    fn term(&self, z: ComplexF64) -> Option<f64> {
+        match self.coloring {
+            AverageColoring::STRIPE(density) => Some(0.5f64 * (density * z.arg()).sin() + 0.5f64),
+            AverageColoring::TRIANGLE => {
+                let previous_norm_sqr = self.previous?.norm_sqr();
+                let lower = (previous_norm_sqr - self.c_norm).abs();
+                let upper = previous_norm_sqr + self.c_norm;
+                if upper > lower {
+                    Some((z.norm() - lower) / (upper - lower))
+                } else {
+                    None
+                }
+            }
+            AverageColoring::CURVATURE => {
+                let previous = self.previous?;
+                let step = previous - self.before_previous?;
+                if step.norm_sqr() == 0f64 {
+                    None
+                } else {
+                    Some(((z - previous) / step).arg().abs() / PI)
+                }
+            }
+        }
+    }
+
+    /// Average over the orbit. For escaped orbits `fraction` is the fractional part of the
+    /// smooth iteration count, blending between the averages with and without the last point.
+    pub(crate) fn finish(&self, fraction: Option<f64>) -> f64 {
+        if self.count == 0 {
+            return 0f64;
+        }
+
+        let average = self.sum / self.count as f64;
+        match fraction {
+            Some(fraction) if self.count > 1 => {
+                let previous_average = (self.sum - self.last_term) / (self.count - 1) as f64;
+                previous_average + (average - previous_average) * fraction
+            }
+            _ => average,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::complex::Complex;
+
+    fn accumulate(coloring: AverageColoring, orbit: &[ComplexF64]) -> AverageAccumulator {
+        let mut accumulator = AverageAccumulator::new(coloring, Complex::zero(), Complex::new(-1f64, 0f64));
+        for z in orbit {
+            accumulator.add(*z);
+        }
+        accumulator
+    }
+
+    #[test]
+    fn stripe_average() {
+        // Arguments `π / 2`, `π` and `-π / 2` give the terms `1.0`, `0.5` and `0.0`.
+        let orbit = [Complex::new(0.0, 1.0), Complex::new(-1.0, 0.0), Complex::new(0.0, -1.0)];
+        let accumulator = accumulate(AverageColoring::STRIPE(1.0), &orbit);
+        assert!((accumulator.finish(None) - 0.5).abs() < 1e-12);
+        // A quarter of the way from the average without the last point, `0.75`.
+        assert!((accumulator.finish(Some(0.25)) - 0.6875).abs() < 1e-12);
+    }
+
+    #[test]
+    fn curvature_average() {
+        let straight = [Complex::new(1.0, 0.0), Complex::new(2.0, 0.0), Complex::new(3.0, 0.0)];
+        assert_eq!(accumulate(AverageColoring::CURVATURE, &straight).finish(None), 0.0);
+        // Steps of `1`, `i` and `-1` turn by a quarter each.
+        let turning = [Complex::new(1.0, 0.0), Complex::new(1.0, 1.0), Complex::new(0.0, 1.0)];
+        assert!((accumulate(AverageColoring::CURVATURE, &turning).finish(None) - 0.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn triangle_average() {
+        // The orbit `i, i - 1, -i, i - 1` of `c = i` lies at `|z|² = 1, 2, 1, 2`, so `|z² + c|` is
+        // bounded by `0..2` and `1..3` in turn. The first point has no bounds, as the start `0` is
+        // too close to `c`.
+        let c = Complex::new(0.0, 1.0);
+        let mut accumulator = AverageAccumulator::new(AverageColoring::TRIANGLE, Complex::zero(), c);
+        let mut z = Complex::zero();
+        for _ in 0..4 {
+            z = z * z + c;
+            accumulator.add(z);
+        }
+        let expected = 2f64.sqrt() / 3.0;
+        assert!((accumulator.finish(None) - expected).abs() < 1e-12, "{}", accumulator.finish(None));
+    }
+
+    #[test]
+    fn empty_orbit_averages_to_zero() {
+        assert_eq!(accumulate(AverageColoring::STRIPE(3.0), &[]).finish(Some(0.5)), 0.0);
+    }
+}
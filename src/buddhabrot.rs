@@ -0,0 +1,325 @@
+use image::Rgb32FImage;
+use rand::Rng;
+use rand_chacha::ChaCha8Rng;
+
+use crate::color::Color;
+use crate::color_palette::ColorPalette;
+use crate::complex::{Complex, ComplexF64};
+use crate::fractal::{resolve_samples, Configuration, Fractal, FractalRepresentation};
+use crate::rendering_settings::RenderingSettings;
+use crate::sampling::accumulate_parallel;
+
+/// Chance of a Metropolis-Hastings step jumping to a random location instead of a nearby one.
+const RESTART_PROBABILITY: f64 = 0.2;
+/// Attempts at finding a contributing starting point for a Metropolis-Hastings chain.
+const START_ATTEMPTS: u32 = 10_000;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum BuddhabrotSampling {
+    /// Samples `c` uniformly over the whole set. Fine for views showing most of the set.
+    UNIFORM,
+    /// Samples `c` proportionally to its contribution to the view, required for zoomed-in views.
+    METROPOLIS,
+}
+
+#[derive(Clone)]
+pub struct BuddhabrotConfiguration {
+    pub center: ComplexF64,
+    pub zoom: f64,
+    /// Iteration limits of the red, green and blue channel.
+    ///
+    /// Different limits produce a Nebulabrot, equal ones the classic Buddhabrot.
+    pub max_iterations: [u32; 3],
+    /// Accumulates the orbits which do not escape instead of the ones which do.
+    pub anti: bool,
+    /// Number of sampled values of `c`.
+    pub samples: u64,
+    pub seed: u64,
+    pub sampling: BuddhabrotSampling,
+}
+
+impl Configuration for BuddhabrotConfiguration {}
+
+pub struct BuddhabrotView {
+    pub configuration: BuddhabrotConfiguration,
+}
+
+impl Fractal<BuddhabrotConfiguration> for BuddhabrotView {
+    type Representation = BuddhabrotRepresentation;
+
+    fn configuration(&self) -> BuddhabrotConfiguration {
+        self.configuration.clone()
+    }
+
+    fn calculate(&self, settings: &RenderingSettings) -> Self::Representation {
+        let configuration = &self.configuration;
+        let channels = accumulate_parallel(
+            configuration.samples,
+            configuration.seed,
+            settings.sample_count() * 3 * size_of::<f64>(),
+            || Channels::new(settings.sample_count()),
+            || None,
+            |channels, chain, random, samples| {
+                accumulate_chunk(configuration, settings, channels, chain, random, samples)
+            },
+            |channels, chunk_channels| channels.add(&chunk_channels),
+        );
+
+        BuddhabrotRepresentation {
+            configuration: configuration.clone(),
+            rendering_settings: *settings,
+            channels: channels.hits,
+        }
+    }
+}
+
+pub struct BuddhabrotRepresentation {
+    configuration: BuddhabrotConfiguration,
+    rendering_settings: RenderingSettings,
+    /// Hit counts of the red, green and blue channel, row-major over the sample grid.
+    pub channels: [Vec<f64>; 3],
+}
+
+impl FractalRepresentation<BuddhabrotConfiguration> for BuddhabrotRepresentation {
+    fn configuration(&self) -> BuddhabrotConfiguration {
+        self.configuration.clone()
+    }
+
+    fn rendering_settings(&self) -> RenderingSettings {
+        self.rendering_settings
+    }
+
+    /// Each channel uses the matching component of `palette` as tone curve over its
+    /// normalized density in `0.0..=1.0`, so a black to white palette gives plain RGB.
+    fn colorize(&self, palette: &impl ColorPalette) -> Rgb32FImage {
+        let maxima = self.channels.each_ref().map(|hits| hits.iter().cloned().fold(0f64, f64::max));
+        let density = |channel: usize, index: usize| {
+            if maxima[channel] > 0f64 {
+                (self.channels[channel][index] / maxima[channel]).sqrt()
+            } else {
+                0f64
+            }
+        };
+
+        resolve_samples(&self.rendering_settings, |index| {
+            Color::new(
+                palette.color_at(density(0, index)).red(),
+                palette.color_at(density(1, index)).green(),
+                palette.color_at(density(2, index)).blue(),
+            )
+        })
+    }
+}
+
+struct Channels {
+    hits: [Vec<f64>; 3],
+}
+
+impl Channels {
+    fn new(size: usize) -> Channels {
+        Channels { hits: [vec![0f64; size], vec![0f64; size], vec![0f64; size]] }
+    }
+
+    fn add(&mut self, other: &Channels) {
+        for (channel, other_channel) in self.hits.iter_mut().zip(&other.hits) {
+            for (hits, other_hits) in channel.iter_mut().zip(other_channel) {
+                *hits += other_hits;
+            }
+        }
+    }
+}
+
+/// Orbit of a single `c` together with the samples it hits inside the view.
+struct Orbit {
+    /// Sample indices of the orbit points, `None` for points outside the view.
+    points: Vec<Option<usize>>,
+    /// Number of points accumulated by each channel.
+    lengths: [usize; 3],
+}
+
+impl Orbit {
+    fn new() -> Orbit {
+        Orbit { points: Vec::new(), lengths: [0; 3] }
+    }
+
+    fn trace(&mut self, configuration: &BuddhabrotConfiguration, settings: &RenderingSettings, c: ComplexF64) {
+        let max_iterations = configuration.max_iterations.iter().cloned().max().unwrap_or(0);
+        self.points.clear();
+
+        let mut z = Complex::zero();
+        let mut escaped = false;
+        while self.points.len() < max_iterations as usize {
+            z = z * z + c;
+            if z.norm_sqr() > 4f64 {
+                escaped = true;
+                break;
+            }
+            self.points.push(settings.sample_index(configuration.center, configuration.zoom, z));
+        }
+
+        for (length, limit) in self.lengths.iter_mut().zip(configuration.max_iterations) {
+            let escaped_in_time = escaped && self.points.len() < limit as usize;
+            *length = if escaped_in_time != configuration.anti {
+                self.points.len().min(limit as usize)
+            } else {
+                0
+            };
+        }
+    }
+
+    /// Number of hits inside the view over all channels.
+    fn contribution(&self) -> usize {
+        self.lengths.iter()
+            .map(|length| self.points[..*length].iter().filter(|point| point.is_some()).count())
+            .sum()
+    }
+
+    fn accumulate(&self, channels: &mut Channels, weight: f64) {
+        for (channel, length) in channels.hits.iter_mut().zip(self.lengths) {
+            for index in self.points[..length].iter().flatten() {
+                channel[*index] += weight;
+            }
+        }
+    }
+}
+
+fn accumulate_chunk(
+    configuration: &BuddhabrotConfiguration,
+    settings: &RenderingSettings,
+    channels: &mut Channels,
+    chain: &mut Option<Chain>,
+    random: &mut ChaCha8Rng,
+    samples: u64,
+) {
+    match configuration.sampling {
+        BuddhabrotSampling::UNIFORM => {
+            let mut orbit = Orbit::new();
+            for _ in 0..samples {
+                orbit.trace(configuration, settings, random_location(random));
+                orbit.accumulate(channels, 1f64);
+            }
+        }
+        BuddhabrotSampling::METROPOLIS => {
+            if chain.is_none() {
+                *chain = Chain::start(configuration, settings, random);
+            }
+            if let Some(chain) = chain {
+                chain.walk(configuration, settings, random, samples, channels);
+            }
+        }
+    }
+}
+
+/// Markov chain over `c` whose stationary distribution is proportional to the contribution of
+/// `c` to the view, kept across chunks so it only has to find the view once.
+struct Chain {
+    current: Orbit,
+    candidate: Orbit,
+    c: ComplexF64,
+    contribution: usize,
+}
+
+impl Chain {
+    /// Chain starting at a random contributing `c`, if one is found.
+    fn start(
+        configuration: &BuddhabrotConfiguration,
+        settings: &RenderingSettings,
+        random: &mut ChaCha8Rng,
+    ) -> Option<Chain> {
+        let mut current = Orbit::new();
+        for _ in 0..START_ATTEMPTS {
+            let c = random_location(random);
+            current.trace(configuration, settings, c);
+            let contribution = current.contribution();
+            if contribution > 0 {
+                return Some(Chain { current, candidate: Orbit::new(), c, contribution });
+            }
+        }
+        None
+    }
+
+    /// Takes `samples` Metropolis-Hastings steps. Orbits are weighted by their inverse
+    /// contribution, so the accumulated density matches uniform sampling.
+    fn walk(
+        &mut self,
+        configuration: &BuddhabrotConfiguration,
+        settings: &RenderingSettings,
+        random: &mut ChaCha8Rng,
+        samples: u64,
+        channels: &mut Channels,
+    ) {
+        let view_size = settings.sample_step(configuration.zoom) * settings.sample_width() as f64;
+        for _ in 0..samples {
+            let candidate_c = if random.gen_bool(RESTART_PROBABILITY) {
+                random_location(random)
+            } else {
+                let radius = view_size * 0.01 * (-4f64 * random.gen::<f64>()).exp();
+                self.c + gaussian(random).scale(radius)
+            };
+
+            self.candidate.trace(configuration, settings, candidate_c);
+            let candidate_contribution = self.candidate.contribution();
+            // Both proposals are symmetric, so the acceptance only depends on the contributions.
+            let acceptance = candidate_contribution as f64 / self.contribution as f64;
+            if candidate_contribution > 0 && random.gen::<f64>() < acceptance {
+                std::mem::swap(&mut self.current, &mut self.candidate);
+                self.c = candidate_c;
+                self.contribution = candidate_contribution;
+            }
+
+            self.current.accumulate(channels, 1f64 / self.contribution as f64);
+        }
+    }
+}
+
+/// Uniformly distributed location in the square containing all escaping orbits.
+fn random_location(random: &mut ChaCha8Rng) -> ComplexF64 {
+    Complex::new(random.gen_range(-2f64..2f64), random.gen_range(-2f64..2f64))
+}
+
+/// Standard normal distributed offset by the Box-Muller transform.
+fn gaussian(random: &mut ChaCha8Rng) -> ComplexF64 {
+    let radius = (-2f64 * (1f64 - random.gen::<f64>()).ln()).sqrt();
+    let angle = std::f64::consts::TAU * random.gen::<f64>();
+    Complex::new(radius * angle.cos(), radius * angle.sin())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rendering_settings::{MultiSampling, Resolution};
+
+    fn render(sampling: BuddhabrotSampling, threads: usize) -> [Vec<f64>; 3] {
+        let view = BuddhabrotView {
+            configuration: BuddhabrotConfiguration {
+                center: Complex::new(-0.5, 0.0),
+                zoom: 1.0,
+                max_iterations: [20, 50, 100],
+                anti: false,
+                samples: 150_000,
+                seed: 7,
+                sampling,
+            },
+        };
+        let settings = RenderingSettings {
+            resolution: Resolution { width: 16, height: 16 },
+            sampling: MultiSampling::NONE,
+        };
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(threads).build().unwrap();
+        pool.install(|| view.calculate(&settings).channels)
+    }
+
+    #[test]
+    fn uniform_sampling_does_not_depend_on_threads() {
+        let single = render(BuddhabrotSampling::UNIFORM, 1);
+        assert!(single[2].iter().any(|hits| *hits > 0.0));
+        assert_eq!(single, render(BuddhabrotSampling::UNIFORM, 4));
+    }
+
+    #[test]
+    fn metropolis_sampling_does_not_depend_on_threads() {
+        let single = render(BuddhabrotSampling::METROPOLIS, 1);
+        assert!(single[2].iter().any(|hits| *hits > 0.0));
+        assert_eq!(single, render(BuddhabrotSampling::METROPOLIS, 4));
+    }
+}
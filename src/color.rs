@@ -1,19 +1,112 @@
+use std::fmt::{Display, Formatter};
+use std::ops::{Add, Mul, Sub};
+use std::str::FromStr;
+
 use interpolatable::Interpolatable;
 use interpolatable::Interpolator;
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
+use crate::color_parsing::{parse_color, ColorParseError};
+use crate::color_space::ColorSpace;
+use crate::encoding::Encoding;
 use crate::interpolatable;
 
+/// Linear light with the sRGB primaries, so averaging and interpolating components is physically
+/// meaningful. Use [Encoding] to convert from and to the values stored in images.
+///
+/// Components are not limited to `0.0..=1.0`: HDR highlights exceed `1.0` and colors outside
+/// the sRGB gamut, see [Gamut](crate::gamut::Gamut), have negative components.
 #[derive(Debug, Clone, Copy, PartialEq, Default)]
 pub struct Color {
-    /// Red component: 0.0 - 1.0
+    /// Red component, 1.0 being reference white
     red: f32,
-    /// Green component: 0.0 - 1.0
+    /// Green component, 1.0 being reference white
     green: f32,
-    /// Blue component: 0.0 - 1.0
+    /// Blue component, 1.0 being reference white
     blue: f32,
 }
 
 impl Color {
+    pub const fn new(red: f32, green: f32, blue: f32) -> Color {
+        Color { red, green, blue }
+    }
+
+    pub fn red(&self) -> f32 {
+        self.red
+    }
+
+    pub fn green(&self) -> f32 {
+        self.green
+    }
+
+    pub fn blue(&self) -> f32 {
+        self.blue
+    }
+
+    /// Components in the order red, green, blue, encoded by `encoding`.
+    pub fn encode(&self, encoding: Encoding) -> [f32; 3] {
+        [self.red, self.green, self.blue].map(|component| encoding.encode(component))
+    }
+
+    /// Color from red, green and blue components encoded by `encoding`.
+    pub fn decode(encoding: Encoding, [red, green, blue]: [f32; 3]) -> Color {
+        Color::new(encoding.decode(red), encoding.decode(green), encoding.decode(blue))
+    }
+
+    /// Components limited to `0.0..=1.0`.
+    pub fn clamp(&self) -> Color {
+        Color::new(self.red.clamp(0f32, 1f32), self.green.clamp(0f32, 1f32), self.blue.clamp(0f32, 1f32))
+    }
+
+    /// Relative luminance as defined by Rec. 709.
+    pub fn luminance(&self) -> f32 {
+        0.2126f32 * self.red + 0.7152f32 * self.green + 0.0722f32 * self.blue
+    }
+
+    /// Lifts the sRGB encoded components by `amount`, so `0.1` brightens shadows and highlights alike.
+    pub fn brightness(&self, amount: f32) -> Color {
+        Color::decode(Encoding::SRGB, self.encode(Encoding::SRGB).map(|component| component + amount))
+    }
+
+    /// Scales the sRGB encoded components around mid gray by `factor`, `1.0` keeping the color.
+    pub fn contrast(&self, factor: f32) -> Color {
+        let encoded = self.encode(Encoding::SRGB).map(|component| (component - 0.5f32) * factor + 0.5f32);
+        Color::decode(Encoding::SRGB, encoded)
+    }
+
+    /// Scales the distance to the gray of the same luminance by `factor`, `0.0` giving grayscale.
+    pub fn saturation(&self, factor: f32) -> Color {
+        let luminance = self.luminance();
+        let gray = Color::new(luminance, luminance, luminance);
+        gray + (*self - gray) * factor
+    }
+
+    /// Rotates the hue by `degrees` in Oklab, keeping perceived lightness and chroma.
+    pub fn rotate_hue(&self, degrees: f32) -> Color {
+        let [lightness, chroma, hue] = ColorSpace::OKLCH.components(*self);
+        ColorSpace::OKLCH.color([lightness, chroma, hue + degrees])
+    }
+
+    /// Multiplies the light by `2^stops`.
+    pub fn exposure(&self, stops: f32) -> Color {
+        *self * stops.exp2()
+    }
+
+    /// Inverts the sRGB encoded components, so mid gray stays mid gray.
+    pub fn invert(&self) -> Color {
+        Color::decode(Encoding::SRGB, self.encode(Encoding::SRGB).map(|component| 1f32 - component))
+    }
+
+    /// Mean of all given colors, or black if there are none.
+    pub fn average(colors: &[Color]) -> Color {
+        if colors.is_empty() {
+            return Color::BLACK;
+        }
+        let sum = colors.iter().fold(Color::BLACK, |sum, color| sum + *color);
+        sum * (1f32 / colors.len() as f32)
+    }
+
     pub const BLACK: Color = Color {
         red: 0f32,
         green: 0f32,
@@ -74,3 +167,149 @@ impl Interpolatable<Color> for Color {
     }
 }
 
+impl Add for Color {
+    type Output = Color;
+
+    fn add(self, other: Color) -> Color {
+        Color::new(self.red + other.red, self.green + other.green, self.blue + other.blue)
+    }
+}
+
+impl Sub for Color {
+    type Output = Color;
+
+    fn sub(self, other: Color) -> Color {
+        Color::new(self.red - other.red, self.green - other.green, self.blue - other.blue)
+    }
+}
+
+impl Mul<f32> for Color {
+    type Output = Color;
+
+    fn mul(self, factor: f32) -> Color {
+        Color::new(self.red * factor, self.green * factor, self.blue * factor)
+    }
+}
+
+/// Component-wise product, e.g. for filtering light through a colored surface.
+impl Mul for Color {
+    type Output = Color;
+
+    fn mul(self, other: Color) -> Color {
+        Color::new(self.red * other.red, self.green * other.green, self.blue * other.blue)
+    }
+}
+
+impl From<Color> for image::Rgb<f32> {
+    fn from(color: Color) -> Self {
+        image::Rgb([color.red, color.green, color.blue])
+    }
+}
+
+/// Parses sRGB encoded CSS colors like `#ff8800`, `rebeccapurple` or `hsl(200, 80%, 50%)`, which must be opaque.
+impl FromStr for Color {
+    type Err = ColorParseError;
+
+    fn from_str(color: &str) -> Result<Self, Self::Err> {
+        let color = parse_color(color)?;
+        if color.alpha() < 1f32 {
+            Err(ColorParseError::NotOpaque)
+        } else {
+            Ok(color.color())
+        }
+    }
+}
+
+/// Serializes as sRGB encoded hex notation if that parses back to exactly this color, otherwise
+/// losslessly as `color(srgb-linear ...)`, e.g. for HDR highlights or interpolated colors.
+impl Serialize for Color {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let hex = self.to_string();
+        if hex.parse::<Color>().ok() == Some(*self) {
+            serializer.collect_str(&hex)
+        } else {
+            serializer.collect_str(&format_args!("color(srgb-linear {} {} {})", self.red, self.green, self.blue))
+        }
+    }
+}
+
+/// Deserializes any notation [Color::from_str] accepts.
+impl<'de> Deserialize<'de> for Color {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?.parse().map_err(D::Error::custom)
+    }
+}
+
+/// Formats as sRGB encoded hex notation, e.g. `#ff8800`.
+impl Display for Color {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let [red, green, blue] = self.encode(Encoding::SRGB).map(to_byte);
+        write!(f, "#{:02x}{:02x}{:02x}", red, green, blue)
+    }
+}
+
+pub(crate) fn to_byte(component: f32) -> u8 {
+    (component.clamp(0f32, 1f32) * 255f32).round() as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: Color, expected: Color) {
+        let difference = [actual.red - expected.red, actual.green - expected.green, actual.blue - expected.blue];
+        assert!(difference.iter().all(|component| component.abs() < 1e-5), "{:?} != {:?}", actual, expected);
+    }
+
+    #[test]
+    fn arithmetic() {
+        let first = Color::new(0.5, 0.25, 1.0);
+        let second = Color::new(0.25, 0.5, 0.5);
+        assert_eq!(first + second, Color::new(0.75, 0.75, 1.5));
+        assert_eq!(first - second, Color::new(0.25, -0.25, 0.5));
+        assert_eq!(first * 2.0, Color::new(1.0, 0.5, 2.0));
+        assert_eq!(first * second, Color::new(0.125, 0.125, 0.5));
+        assert_eq!(Color::average(&[first, second]), Color::new(0.375, 0.375, 0.75));
+        assert_eq!(Color::average(&[]), Color::BLACK);
+    }
+
+    #[test]
+    fn luminance() {
+        assert!((Color::WHITE.luminance() - 1.0).abs() < 1e-6);
+        assert_eq!(Color::GREEN.luminance(), 0.7152);
+        assert_eq!(Color::RED.luminance(), 0.2126);
+        assert_eq!(Color::BLUE.luminance(), 0.0722);
+    }
+
+    #[test]
+    fn clamp() {
+        assert_eq!(Color::new(-0.5, 0.5, 2.0).clamp(), Color::new(0.0, 0.5, 1.0));
+    }
+
+    #[test]
+    fn exposure() {
+        let color = Color::new(0.1, 0.2, 0.4);
+        assert_eq!(color.exposure(1.0), Color::new(0.2, 0.4, 0.8));
+        assert_eq!(color.exposure(-2.0), Color::new(0.025, 0.05, 0.1));
+        assert_eq!(color.exposure(0.0), color);
+    }
+
+    #[test]
+    fn saturation() {
+        let color = Color::new(0.8, 0.4, 0.2);
+        let luminance = color.luminance();
+        assert_close(color.saturation(0.0), Color::new(luminance, luminance, luminance));
+        assert_close(color.saturation(1.0), color);
+        assert!((color.saturation(2.0).luminance() - luminance).abs() < 1e-6);
+    }
+
+    #[test]
+    fn contrast_and_brightness() {
+        let mid_gray = Color::decode(Encoding::SRGB, [0.5, 0.5, 0.5]);
+        assert_close(mid_gray.contrast(3.0), mid_gray);
+        assert_close(Color::new(0.2, 0.4, 0.6).contrast(1.0), Color::new(0.2, 0.4, 0.6));
+        assert_close(Color::WHITE.contrast(0.0), mid_gray);
+        assert_close(Color::BLACK.brightness(0.5), mid_gray);
+        assert_close(mid_gray.invert(), mid_gray);
+    }
+}
@@ -1,81 +1,465 @@
-use std::collections::BTreeMap;
-use std::ops::Index;
+use std::cmp::Ordering;
 
 use color::Color;
+use serde::{Deserialize, Serialize};
 
 use crate::color;
-use crate::interpolatable::{InterpolatableLocation, Interpolation};
+use crate::color_space::ColorSpace;
+use crate::interpolatable::Interpolation;
+
+/// Maps indices, e.g. smooth iteration counts, to colors.
+///
+/// Palettes can not implement `Index`, as that has to return a reference, while interpolated
+/// colors are new values.
+pub trait ColorPalette {
+    /// Color at `index`, interpolated between the key colors it lies between.
+    ///
+    /// [RepeatingColorPalette] repeats over `0.0..last`, not `first..last`, so its first key
+    /// should be at `0.0`: positions below a later first key take the color of that key.
+    fn color_at(&self, index: f64) -> Color;
 
-pub trait ColorPalette: Index<f64> {
     fn set_max(&mut self, max: f64);
+
+    /// How indices are mapped onto the key colors.
+    fn mapping(&self) -> PaletteMapping;
+
+    fn set_mapping(&mut self, mapping: PaletteMapping);
+}
+
+/// Maps palette indices to key positions as `index * speed + offset`, mirrored over the range
+/// of the keys when running in [PaletteDirection::REVERSE].
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct PaletteMapping {
+    /// Shift in key positions, which palette cycling animates.
+    pub offset: f64,
+    /// How fast indices run through the palette, also known as color density.
+    pub speed: f64,
+    pub direction: PaletteDirection,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PaletteDirection {
+    FORWARD,
+    REVERSE,
+}
+
+impl PaletteMapping {
+    /// Key position of `index` for keys from `first` to `last`.
+    pub fn position(&self, index: f64, first: f64, last: f64) -> f64 {
+        let position = index * self.speed + self.offset;
+        match self.direction {
+            PaletteDirection::FORWARD => position,
+            PaletteDirection::REVERSE => first + last - position,
+        }
+    }
+}
+
+impl Default for PaletteMapping {
+    fn default() -> Self {
+        PaletteMapping { offset: 0f64, speed: 1f64, direction: PaletteDirection::FORWARD }
+    }
 }
 
 pub struct RepeatingColorPalette {
     pub interpolation: Interpolation,
-    pub key_colors: BTreeMap<f64, Color>,
+    /// Space the key colors are interpolated in.
+    pub space: ColorSpace,
+    pub key_colors: KeyColors,
+    pub mapping: PaletteMapping,
 }
 
 pub struct ScalingColorPalette {
     pub interpolation: Interpolation,
-    pub key_colors: BTreeMap<f64, Color>,
+    /// Space the key colors are interpolated in.
+    pub space: ColorSpace,
+    pub key_colors: KeyColors,
     pub scale: f64,
+    pub mapping: PaletteMapping,
 }
 
-impl Index<f64> for RepeatingColorPalette {
-    type Output = Color;
+/// Colors at key positions, sorted by position.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct KeyColors {
+    keys: Vec<(f64, Color)>,
+}
 
-    fn index(&self, index: f64) -> &Self::Output {
-        let index = index % self.key_colors.keys().max().unwrap();
-        get_color_from_keys(&self.key_colors, index, &self.interpolation)
+impl KeyColors {
+    pub fn new() -> KeyColors {
+        KeyColors { keys: Vec::new() }
+    }
+
+    /// Adds a key color, returning the color it replaced at the same position.
+    pub fn insert(&mut self, position: f64, color: Color) -> Option<Color> {
+        match self.search(position) {
+            Ok(index) => Some(std::mem::replace(&mut self.keys[index].1, color)),
+            Err(index) => {
+                self.keys.insert(index, (position, color));
+                None
+            }
+        }
+    }
+
+    pub fn get(&self, position: f64) -> Option<Color> {
+        self.search(position).ok().map(|index| self.keys[index].1)
+    }
+
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item=(f64, Color)> + ExactSizeIterator + '_ {
+        self.keys.iter().copied()
+    }
+
+    pub fn positions(&self) -> impl DoubleEndedIterator<Item=f64> + ExactSizeIterator + '_ {
+        self.keys.iter().map(|(position, _)| *position)
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Positions of the first and last key.
+    pub fn range(&self) -> Option<(f64, f64)> {
+        Some((self.keys.first()?.0, self.keys.last()?.0))
+    }
+
+    /// Color at `position`, interpolated in `space` between the neighbouring keys. Before the first
+    /// and after the last key, the color of that key continues.
+    ///
+    /// # Panics
+    /// If there are no key colors.
+    pub fn color_at(&self, position: f64, interpolation: Interpolation, space: ColorSpace) -> Color {
+        let next = match self.search(position) {
+            Ok(index) => return self.keys[index].1,
+            Err(next) => next,
+        };
+        match (next.checked_sub(1).map(|previous| self.keys[previous]), self.keys.get(next)) {
+            (Some((first, first_color)), Some(&(second, second_color))) => {
+                let ratio = (position - first) / (second - first);
+                space.interpolate(interpolation, first_color, ratio, second_color)
+            }
+            (Some((_, color)), None) | (None, Some(&(_, color))) => color,
+            (None, None) => panic!("ColorPalette has no key colors"),
+        }
+    }
+
+    /// Index of the key at `position`, or the index a key at `position` would be inserted at.
+    /// Positions are ordered by [f64::total_cmp].
+    fn search(&self, position: f64) -> Result<usize, usize> {
+        self.keys.binary_search_by(|(key, _)| match key.total_cmp(&position) {
+            // Treats `-0.0` and `0.0` as the same position.
+            Ordering::Less | Ordering::Greater if *key == position => Ordering::Equal,
+            ordering => ordering,
+        })
+    }
+}
+
+impl FromIterator<(f64, Color)> for KeyColors {
+    fn from_iter<I: IntoIterator<Item=(f64, Color)>>(keys: I) -> Self {
+        let mut key_colors = KeyColors::new();
+        for (position, color) in keys {
+            key_colors.insert(position, color);
+        }
+        key_colors
+    }
+}
+
+impl<const N: usize> From<[(f64, Color); N]> for KeyColors {
+    fn from(keys: [(f64, Color); N]) -> Self {
+        keys.into_iter().collect()
+    }
+}
+
+/// Key colors of a palette independent of how it is indexed, with key positions usually in `0.0..=1.0`.
+///
+/// This is the exchange format of palette files, see [palette_file](crate::palette_file), which
+/// serializes as
+///
+/// ```toml
+/// name = "sunset"
+/// interpolation = "cubic"
+/// space = "oklab"
+/// keys = [
+///     { position = 0.0, color = "midnightblue" },
+///     { position = 1.0, color = "hsl(30 100% 60%)" },
+/// ]
+/// ```
+///
+/// with colors in any notation [Color] parses. Interpolation and space default to linear RGB.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PaletteDefinition {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub interpolation: Interpolation,
+    #[serde(default)]
+    pub space: ColorSpace,
+    #[serde(rename = "keys", with = "key_list")]
+    pub key_colors: KeyColors,
+}
+
+/// Key colors as list of `{ position, color }`, as TOML and JSON only allow strings as map keys.
+mod key_list {
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use crate::color::Color;
+    use crate::color_palette::KeyColors;
+
+    #[derive(Serialize, Deserialize)]
+    struct Key {
+        position: f64,
+        color: Color,
+    }
+
+    pub fn serialize<S: Serializer>(keys: &KeyColors, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(keys.iter().map(|(position, color)| Key { position, color }))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<KeyColors, D::Error> {
+        let keys = Vec::<Key>::deserialize(deserializer)?;
+        if keys.is_empty() {
+            return Err(D::Error::custom("palette has no key colors"));
+        }
+        Ok(keys.into_iter().map(|key| (key.position, key.color)).collect())
+    }
+}
+
+impl PaletteDefinition {
+    /// Palette repeating every `1.0`, given the last key is at `1.0`.
+    pub fn repeating(self) -> RepeatingColorPalette {
+        RepeatingColorPalette {
+            interpolation: self.interpolation,
+            space: self.space,
+            key_colors: self.key_colors,
+            mapping: PaletteMapping::default(),
+        }
+    }
+
+    /// Palette stretched over the range passed to [ColorPalette::set_max].
+    pub fn scaling(self) -> ScalingColorPalette {
+        ScalingColorPalette {
+            interpolation: self.interpolation,
+            space: self.space,
+            key_colors: self.key_colors,
+            scale: 1f64,
+            mapping: PaletteMapping::default(),
+        }
+    }
+}
+
+impl From<&RepeatingColorPalette> for PaletteDefinition {
+    fn from(palette: &RepeatingColorPalette) -> Self {
+        PaletteDefinition {
+            name: None,
+            interpolation: palette.interpolation,
+            space: palette.space,
+            key_colors: palette.key_colors.clone(),
+        }
+    }
+}
+
+impl From<&ScalingColorPalette> for PaletteDefinition {
+    fn from(palette: &ScalingColorPalette) -> Self {
+        PaletteDefinition {
+            name: None,
+            interpolation: palette.interpolation,
+            space: palette.space,
+            key_colors: palette.key_colors.clone(),
+        }
     }
 }
 
 impl ColorPalette for RepeatingColorPalette {
+    /// Repeats every last key position, with the first key usually at `0.0`.
+    fn color_at(&self, index: f64) -> Color {
+        let (first, last) = self.key_colors.range().unwrap_or_default();
+        let mut position = self.mapping.position(index, first, last);
+        if last > 0f64 {
+            position = position.rem_euclid(last);
+        }
+        self.key_colors.color_at(position, self.interpolation, self.space)
+    }
+
     fn set_max(&mut self, _: f64) {}
-}
 
-impl Index<f64> for ScalingColorPalette {
-    type Output = Color;
+    fn mapping(&self) -> PaletteMapping {
+        self.mapping
+    }
 
-    fn index(&self, index: f64) -> &Self::Output {
-        let index = index / self.scale;
-        get_color_from_keys(&self.key_colors, index, &self.interpolation)
+    fn set_mapping(&mut self, mapping: PaletteMapping) {
+        self.mapping = mapping;
     }
 }
 
 impl ColorPalette for ScalingColorPalette {
+    fn color_at(&self, index: f64) -> Color {
+        let (first, last) = self.key_colors.range().unwrap_or_default();
+        let position = self.mapping.position(index / self.scale, first, last);
+        self.key_colors.color_at(position, self.interpolation, self.space)
+    }
+
     fn set_max(&mut self, max: f64) {
         self.scale = max;
     }
+
+    fn mapping(&self) -> PaletteMapping {
+        self.mapping
+    }
+
+    fn set_mapping(&mut self, mapping: PaletteMapping) {
+        self.mapping = mapping;
+    }
 }
 
-fn get_color_from_keys(
-    keys: &BTreeMap<f64, Color>,
-    index: f64,
-    interpolation: &Interpolation,
-) -> &Color {
-    if let Some(mid) = keys.get(&index) {
-        return mid;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encoding::Encoding;
+
+    fn definition() -> PaletteDefinition {
+        PaletteDefinition {
+            name: None,
+            interpolation: Interpolation::LINEAR,
+            space: ColorSpace::RGB,
+            key_colors: KeyColors::from([(0.25, Color::BLACK), (0.5, Color::RED), (1.0, Color::WHITE)]),
+        }
     }
 
-    let first = keys.range(..index).next_back();
-    let second = keys.range(index..).next();
+    fn assert_color(actual: Color, expected: Color) {
+        let difference = [
+            actual.red() - expected.red(),
+            actual.green() - expected.green(),
+            actual.blue() - expected.blue(),
+        ];
+        assert!(difference.iter().all(|component| component.abs() < 1e-5), "{:?} != {:?}", actual, expected);
+    }
 
-    if let (Some((index_first, color_first)), Some((index_second, color_second))) = (first, second) {
-        let left_interpolatable = InterpolatableLocation {
-            value: color_first,
-            location: *index_first,
-        };
-        let right_interpolatable = InterpolatableLocation {
-            value: color_second,
-            location: *index_second,
-        };
-        interpolation.interpolate(left_interpolatable, index, right_interpolatable)
-    } else if let Some((_, color_first)) = first {
-        color_first
-    } else if let Some((_, color_second)) = second {
-        color_second
-    } else {
-        panic!("ColorPalette has no key colors")
-    }
-}
\ No newline at end of file
+    #[test]
+    fn definition_round_trips() {
+        let mut palette = definition();
+        palette.key_colors.insert(0.1, Color::new(0.1234567, 0.5, 0.9876543));
+        palette.key_colors.insert(0.75, Color::new(2.5, 1.0, 0.0));
+        palette.key_colors.insert(0.8, Color::decode(Encoding::SRGB, [1.0, 128.0 / 255.0, 0.0]));
+
+        let toml = toml::to_string(&palette).unwrap();
+        assert_eq!(toml::from_str::<PaletteDefinition>(&toml).unwrap().key_colors, palette.key_colors);
+        let json = serde_json::to_string(&palette).unwrap();
+        assert!(json.contains("\"#ff8000\""), "{}", json);
+        assert_eq!(serde_json::from_str::<PaletteDefinition>(&json).unwrap().key_colors, palette.key_colors);
+        let ron = ron::to_string(&palette).unwrap();
+        assert_eq!(ron::from_str::<PaletteDefinition>(&ron).unwrap().key_colors, palette.key_colors);
+    }
+
+    #[test]
+    fn repeating_at_keys() {
+        let palette = definition().repeating();
+        assert_color(palette.color_at(0.25), Color::BLACK);
+        assert_color(palette.color_at(0.5), Color::RED);
+        assert_color(palette.color_at(1.5), Color::RED);
+    }
+
+    #[test]
+    fn repeating_between_keys() {
+        let palette = definition().repeating();
+        assert_color(palette.color_at(0.375), Color::new(0.5, 0.0, 0.0));
+        assert_color(palette.color_at(0.75), Color::new(1.0, 0.5, 0.5));
+        assert_color(palette.color_at(2.75), Color::new(1.0, 0.5, 0.5));
+    }
+
+    #[test]
+    fn repeating_before_first_key() {
+        let palette = definition().repeating();
+        assert_color(palette.color_at(0.1), Color::BLACK);
+        assert_color(palette.color_at(-0.5), Color::RED);
+    }
+
+    #[test]
+    fn repeating_after_last_key() {
+        let palette = definition().repeating();
+        assert_color(palette.color_at(1.1), Color::BLACK);
+        assert_color(palette.color_at(1.75), Color::new(1.0, 0.5, 0.5));
+    }
+
+    #[test]
+    fn repeating_with_offset() {
+        let mut palette = definition().repeating();
+        palette.set_mapping(PaletteMapping { offset: 0.25, ..PaletteMapping::default() });
+        assert_color(palette.color_at(0.25), Color::RED);
+        assert_color(palette.color_at(0.5), Color::new(1.0, 0.5, 0.5));
+        assert_color(palette.color_at(1.25), Color::RED);
+    }
+
+    #[test]
+    fn repeating_with_speed() {
+        let mut palette = definition().repeating();
+        palette.set_mapping(PaletteMapping { speed: 2.0, ..PaletteMapping::default() });
+        assert_color(palette.color_at(0.25), Color::RED);
+        assert_color(palette.color_at(0.375), Color::new(1.0, 0.5, 0.5));
+        palette.set_mapping(PaletteMapping { speed: -1.0, ..PaletteMapping::default() });
+        assert_color(palette.color_at(-0.5), Color::RED);
+    }
+
+    #[test]
+    fn repeating_reversed() {
+        let mut palette = definition().repeating();
+        palette.set_mapping(PaletteMapping { direction: PaletteDirection::REVERSE, ..PaletteMapping::default() });
+        // Mirrored over the keys from `0.25` to `1.0`.
+        assert_color(palette.color_at(0.375), Color::new(1.0, 0.75, 0.75));
+        assert_color(palette.color_at(0.75), Color::RED);
+        assert_color(palette.color_at(1.0), Color::BLACK);
+    }
+
+    #[test]
+    fn scaling_with_mapping() {
+        let mut palette = definition().scaling();
+        palette.set_max(10.0);
+        palette.set_mapping(PaletteMapping { offset: 0.25, speed: 0.5, direction: PaletteDirection::FORWARD });
+        assert_color(palette.color_at(5.0), Color::RED);
+        palette.set_mapping(PaletteMapping { offset: 0.0, speed: 1.0, direction: PaletteDirection::REVERSE });
+        assert_color(palette.color_at(10.0), Color::BLACK);
+        assert_color(palette.color_at(7.5), Color::RED);
+    }
+
+    #[test]
+    fn scaling_at_keys() {
+        let mut palette = definition().scaling();
+        palette.set_max(10.0);
+        assert_color(palette.color_at(2.5), Color::BLACK);
+        assert_color(palette.color_at(5.0), Color::RED);
+        assert_color(palette.color_at(10.0), Color::WHITE);
+    }
+
+    #[test]
+    fn scaling_between_keys() {
+        let mut palette = definition().scaling();
+        palette.set_max(10.0);
+        assert_color(palette.color_at(3.75), Color::new(0.5, 0.0, 0.0));
+        assert_color(palette.color_at(7.5), Color::new(1.0, 0.5, 0.5));
+    }
+
+    #[test]
+    fn scaling_before_first_key() {
+        let mut palette = definition().scaling();
+        palette.set_max(10.0);
+        assert_color(palette.color_at(1.0), Color::BLACK);
+        assert_color(palette.color_at(-5.0), Color::BLACK);
+    }
+
+    #[test]
+    fn scaling_after_last_key() {
+        let mut palette = definition().scaling();
+        palette.set_max(10.0);
+        assert_color(palette.color_at(12.0), Color::WHITE);
+        assert_color(palette.color_at(f64::INFINITY), Color::WHITE);
+    }
+
+    #[test]
+    fn cubic_between_keys() {
+        let mut palette = definition().scaling();
+        palette.interpolation = Interpolation::CUBIC;
+        assert_color(palette.color_at(0.375), Color::new(0.5, 0.0, 0.0));
+        assert_color(palette.color_at(0.3125), Color::new(0.15625, 0.0, 0.0));
+    }
+}
@@ -0,0 +1,400 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+use crate::alpha_color::AlphaColor;
+use crate::color::Color;
+use crate::color_space::ColorSpace;
+use crate::encoding::Encoding;
+use crate::gamut::Gamut;
+
+/// Named colors of the CSS Color Module Level 4, sorted by name.
+const NAMED_COLORS: [(&str, u32); 148] = [
+    ("aliceblue", 0xf0f8ff),
+    ("antiquewhite", 0xfaebd7),
+    ("aqua", 0x00ffff),
+    ("aquamarine", 0x7fffd4),
+    ("azure", 0xf0ffff),
+    ("beige", 0xf5f5dc),
+    ("bisque", 0xffe4c4),
+    ("black", 0x000000),
+    ("blanchedalmond", 0xffebcd),
+    ("blue", 0x0000ff),
+    ("blueviolet", 0x8a2be2),
+    ("brown", 0xa52a2a),
+    ("burlywood", 0xdeb887),
+    ("cadetblue", 0x5f9ea0),
+    ("chartreuse", 0x7fff00),
+    ("chocolate", 0xd2691e),
+    ("coral", 0xff7f50),
+    ("cornflowerblue", 0x6495ed),
+    ("cornsilk", 0xfff8dc),
+    ("crimson", 0xdc143c),
+    ("cyan", 0x00ffff),
+    ("darkblue", 0x00008b),
+    ("darkcyan", 0x008b8b),
+    ("darkgoldenrod", 0xb8860b),
+    ("darkgray", 0xa9a9a9),
+    ("darkgreen", 0x006400),
+    ("darkgrey", 0xa9a9a9),
+    ("darkkhaki", 0xbdb76b),
+    ("darkmagenta", 0x8b008b),
+    ("darkolivegreen", 0x556b2f),
+    ("darkorange", 0xff8c00),
+    ("darkorchid", 0x9932cc),
+    ("darkred", 0x8b0000),
+    ("darksalmon", 0xe9967a),
+    ("darkseagreen", 0x8fbc8f),
+    ("darkslateblue", 0x483d8b),
+    ("darkslategray", 0x2f4f4f),
+    ("darkslategrey", 0x2f4f4f),
+    ("darkturquoise", 0x00ced1),
+    ("darkviolet", 0x9400d3),
+    ("deeppink", 0xff1493),
+    ("deepskyblue", 0x00bfff),
+    ("dimgray", 0x696969),
+    ("dimgrey", 0x696969),
+    ("dodgerblue", 0x1e90ff),
+    ("firebrick", 0xb22222),
+    ("floralwhite", 0xfffaf0),
+    ("forestgreen", 0x228b22),
+    ("fuchsia", 0xff00ff),
+    ("gainsboro", 0xdcdcdc),
+    ("ghostwhite", 0xf8f8ff),
+    ("gold", 0xffd700),
+    ("goldenrod", 0xdaa520),
+    ("gray", 0x808080),
+    ("green", 0x008000),
+    ("greenyellow", 0xadff2f),
+    ("grey", 0x808080),
+    ("honeydew", 0xf0fff0),
+    ("hotpink", 0xff69b4),
+    ("indianred", 0xcd5c5c),
+    ("indigo", 0x4b0082),
+    ("ivory", 0xfffff0),
+    ("khaki", 0xf0e68c),
+    ("lavender", 0xe6e6fa),
+    ("lavenderblush", 0xfff0f5),
+    ("lawngreen", 0x7cfc00),
+    ("lemonchiffon", 0xfffacd),
+    ("lightblue", 0xadd8e6),
+    ("lightcoral", 0xf08080),
+    ("lightcyan", 0xe0ffff),
+    ("lightgoldenrodyellow", 0xfafad2),
+    ("lightgray", 0xd3d3d3),
+    ("lightgreen", 0x90ee90),
+    ("lightgrey", 0xd3d3d3),
+    ("lightpink", 0xffb6c1),
+    ("lightsalmon", 0xffa07a),
+    ("lightseagreen", 0x20b2aa),
+    ("lightskyblue", 0x87cefa),
+    ("lightslategray", 0x778899),
+    ("lightslategrey", 0x778899),
+    ("lightsteelblue", 0xb0c4de),
+    ("lightyellow", 0xffffe0),
+    ("lime", 0x00ff00),
+    ("limegreen", 0x32cd32),
+    ("linen", 0xfaf0e6),
+    ("magenta", 0xff00ff),
+    ("maroon", 0x800000),
+    ("mediumaquamarine", 0x66cdaa),
+    ("mediumblue", 0x0000cd),
+    ("mediumorchid", 0xba55d3),
+    ("mediumpurple", 0x9370db),
+    ("mediumseagreen", 0x3cb371),
+    ("mediumslateblue", 0x7b68ee),
+    ("mediumspringgreen", 0x00fa9a),
+    ("mediumturquoise", 0x48d1cc),
+    ("mediumvioletred", 0xc71585),
+    ("midnightblue", 0x191970),
+    ("mintcream", 0xf5fffa),
+    ("mistyrose", 0xffe4e1),
+    ("moccasin", 0xffe4b5),
+    ("navajowhite", 0xffdead),
+    ("navy", 0x000080),
+    ("oldlace", 0xfdf5e6),
+    ("olive", 0x808000),
+    ("olivedrab", 0x6b8e23),
+    ("orange", 0xffa500),
+    ("orangered", 0xff4500),
+    ("orchid", 0xda70d6),
+    ("palegoldenrod", 0xeee8aa),
+    ("palegreen", 0x98fb98),
+    ("paleturquoise", 0xafeeee),
+    ("palevioletred", 0xdb7093),
+    ("papayawhip", 0xffefd5),
+    ("peachpuff", 0xffdab9),
+    ("peru", 0xcd853f),
+    ("pink", 0xffc0cb),
+    ("plum", 0xdda0dd),
+    ("powderblue", 0xb0e0e6),
+    ("purple", 0x800080),
+    ("rebeccapurple", 0x663399),
+    ("red", 0xff0000),
+    ("rosybrown", 0xbc8f8f),
+    ("royalblue", 0x4169e1),
+    ("saddlebrown", 0x8b4513),
+    ("salmon", 0xfa8072),
+    ("sandybrown", 0xf4a460),
+    ("seagreen", 0x2e8b57),
+    ("seashell", 0xfff5ee),
+    ("sienna", 0xa0522d),
+    ("silver", 0xc0c0c0),
+    ("skyblue", 0x87ceeb),
+    ("slateblue", 0x6a5acd),
+    ("slategray", 0x708090),
+    ("slategrey", 0x708090),
+    ("snow", 0xfffafa),
+    ("springgreen", 0x00ff7f),
+    ("steelblue", 0x4682b4),
+    ("tan", 0xd2b48c),
+    ("teal", 0x008080),
+    ("thistle", 0xd8bfd8),
+    ("tomato", 0xff6347),
+    ("turquoise", 0x40e0d0),
+    ("violet", 0xee82ee),
+    ("wheat", 0xf5deb3),
+    ("white", 0xffffff),
+    ("whitesmoke", 0xf5f5f5),
+    ("yellow", 0xffff00),
+    ("yellowgreen", 0x9acd32),
+];
+
+/// Parses an sRGB encoded CSS color: hex notation with 3, 4, 6 or 8 digits, a named color, `transparent`,
+/// or one of the functions `rgb()`, `rgba()`, `hsl()` and `hsla()` in comma or space separated syntax.
+///
+/// Wide-gamut colors are given by `color()` with one of the spaces `srgb`, `srgb-linear`,
+/// `display-p3`, `rec2020` and the non-standard `acescg`, e.g. `color(display-p3 1 0.5 0 / 50%)`.
+/// Their components may exceed `1.0` for HDR highlights.
+pub(crate) fn parse_color(input: &str) -> Result<AlphaColor, ColorParseError> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Err(ColorParseError::Empty);
+    }
+
+    if let Some(digits) = input.strip_prefix('#') {
+        return parse_hex(digits);
+    }
+    if let Some(open) = input.find('(') {
+        let function = input[..open].trim().to_ascii_lowercase();
+        let arguments = input[open + 1..].strip_suffix(')').ok_or(ColorParseError::MissingParenthesis)?;
+        return parse_function(&function, arguments);
+    }
+
+    let name = input.to_ascii_lowercase();
+    if name == "transparent" {
+        return Ok(AlphaColor::TRANSPARENT);
+    }
+    NAMED_COLORS.binary_search_by_key(&name.as_str(), |(name, _)| name)
+        .map(|index| {
+            let [_, red, green, blue] = NAMED_COLORS[index].1.to_be_bytes();
+            srgb([byte(red), byte(green), byte(blue)], 1f32)
+        })
+        .map_err(|_| ColorParseError::UnknownName(input.to_string()))
+}
+
+fn byte(value: u8) -> f32 {
+    value as f32 / 255f32
+}
+
+fn srgb(components: [f32; 3], alpha: f32) -> AlphaColor {
+    AlphaColor::with_alpha(Color::decode(Encoding::SRGB, components), alpha)
+}
+
+fn parse_hex(digits: &str) -> Result<AlphaColor, ColorParseError> {
+    let values = digits.chars()
+        .enumerate()
+        .map(|(position, character)| {
+            character.to_digit(16)
+                .map(|value| value as u8)
+                .ok_or(ColorParseError::InvalidHexDigit { character, position: position + 1 })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let components: Vec<u8> = match values.len() {
+        3 | 4 => values.iter().map(|value| value * 17).collect(),
+        6 | 8 => values.chunks(2).map(|pair| pair[0] * 16 + pair[1]).collect(),
+        length => return Err(ColorParseError::InvalidHexLength(length)),
+    };
+    let alpha = components.get(3).map_or(1f32, |alpha| byte(*alpha));
+    Ok(srgb([byte(components[0]), byte(components[1]), byte(components[2])], alpha))
+}
+
+fn parse_function(function: &str, arguments: &str) -> Result<AlphaColor, ColorParseError> {
+    let (space, arguments) = match function {
+        "color" => {
+            let arguments = arguments.trim_start();
+            let end = arguments.find(char::is_whitespace).unwrap_or(arguments.len());
+            (Some(arguments[..end].to_ascii_lowercase()), &arguments[end..])
+        }
+        _ => (None, arguments),
+    };
+
+    let arguments: Vec<&str> = if arguments.contains(',') {
+        arguments.split(',').map(str::trim).collect()
+    } else {
+        // Space separated syntax, with the alpha after a slash.
+        arguments.split(|character: char| character.is_whitespace() || character == '/')
+            .filter(|argument| !argument.is_empty())
+            .collect()
+    };
+    if arguments.len() != 3 && arguments.len() != 4 {
+        return Err(ColorParseError::ArgumentCount { function: function.to_string(), found: arguments.len() });
+    }
+
+    let invalid = |argument: &str| ColorParseError::InvalidArgument {
+        function: function.to_string(),
+        argument: argument.to_string(),
+    };
+    let alpha = match arguments.get(3) {
+        Some(alpha) => fraction(alpha, 1f32).ok_or_else(|| invalid(alpha))?,
+        None => 1f32,
+    };
+
+    if let Some(space) = space {
+        let (gamut, encoding) = match space.as_str() {
+            "srgb" => (Gamut::SRGB, Encoding::SRGB),
+            "srgb-linear" => (Gamut::SRGB, Encoding::LINEAR),
+            "display-p3" => (Gamut::DISPLAYP3, Encoding::SRGB),
+            "rec2020" => (Gamut::REC2020, Encoding::REC709),
+            "acescg" => (Gamut::ACESCG, Encoding::LINEAR),
+            _ => return Err(ColorParseError::UnknownSpace(space)),
+        };
+        let component = |argument: &str| {
+            fraction(argument, 1f32).map(|value| encoding.decode(value)).ok_or_else(|| invalid(argument))
+        };
+        let components = [component(arguments[0])?, component(arguments[1])?, component(arguments[2])?];
+        return Ok(AlphaColor::with_alpha(gamut.color(components), alpha));
+    }
+
+    match function {
+        "rgb" | "rgba" => {
+            let channel = |argument: &str| fraction(argument, 255f32).ok_or_else(|| invalid(argument));
+            Ok(srgb([channel(arguments[0])?, channel(arguments[1])?, channel(arguments[2])?], alpha))
+        }
+        "hsl" | "hsla" => {
+            let hue = angle(arguments[0]).ok_or_else(|| invalid(arguments[0]))?;
+            let percentage = |argument: &str| fraction(argument, 100f32).ok_or_else(|| invalid(argument));
+            let color = ColorSpace::HSL.color([hue, percentage(arguments[1])?, percentage(arguments[2])?]);
+            Ok(AlphaColor::with_alpha(color, alpha))
+        }
+        _ => Err(ColorParseError::UnknownFunction(function.to_string())),
+    }
+}
+
+/// Percentage, or a number relative to `scale`, as fraction of the full range.
+fn fraction(argument: &str, scale: f32) -> Option<f32> {
+    match argument.strip_suffix('%') {
+        Some(percentage) => percentage.trim().parse::<f32>().ok().map(|value| value / 100f32),
+        None => argument.parse::<f32>().ok().map(|value| value / scale),
+    }
+}
+
+/// Hue in degrees, from a number of degrees or an angle with unit.
+fn angle(argument: &str) -> Option<f32> {
+    let units = [("deg", 1f32), ("grad", 0.9f32), ("rad", 180f32 / std::f32::consts::PI), ("turn", 360f32)];
+    for (unit, degrees) in units {
+        if let Some(value) = argument.strip_suffix(unit) {
+            return value.trim().parse::<f32>().ok().map(|value| value * degrees);
+        }
+    }
+    argument.parse().ok()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ColorParseError {
+    Empty,
+    InvalidHexLength(usize),
+    /// `position` counts the characters after the `#`, starting at `1`.
+    InvalidHexDigit { character: char, position: usize },
+    UnknownName(String),
+    UnknownFunction(String),
+    /// Unsupported color space in the `color()` function.
+    UnknownSpace(String),
+    MissingParenthesis,
+    ArgumentCount { function: String, found: usize },
+    InvalidArgument { function: String, argument: String },
+    /// The color has an alpha below `1.0`, but was parsed as opaque color.
+    NotOpaque,
+}
+
+impl Display for ColorParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ColorParseError::Empty => write!(f, "color must not be empty"),
+            ColorParseError::InvalidHexLength(length) => {
+                write!(f, "expected 3, 4, 6 or 8 hex digits, found {}", length)
+            }
+            ColorParseError::InvalidHexDigit { character, position } => {
+                write!(f, "invalid hex digit `{}` at position {}", character, position)
+            }
+            ColorParseError::UnknownName(name) => write!(f, "unknown color name `{}`", name),
+            ColorParseError::UnknownFunction(function) => {
+                write!(f, "unknown color function `{}`, expected `rgb`, `rgba`, `hsl`, `hsla` or `color`", function)
+            }
+            ColorParseError::UnknownSpace(space) => {
+                let expected = "`srgb`, `srgb-linear`, `display-p3`, `rec2020` or `acescg`";
+                write!(f, "unknown color space `{}`, expected {}", space, expected)
+            }
+            ColorParseError::MissingParenthesis => write!(f, "missing closing parenthesis"),
+            ColorParseError::ArgumentCount { function, found } => {
+                write!(f, "`{}` expects 3 or 4 arguments, found {}", function, found)
+            }
+            ColorParseError::InvalidArgument { function, argument } => {
+                write!(f, "invalid argument `{}` to `{}`", argument, function)
+            }
+            ColorParseError::NotOpaque => write!(f, "color must be opaque"),
+        }
+    }
+}
+
+impl Error for ColorParseError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::to_byte;
+
+    /// sRGB encoded bytes and alpha of a parsed color.
+    fn parse_bytes(input: &str) -> ([u8; 3], f32) {
+        let color = parse_color(input).unwrap();
+        (color.color().encode(Encoding::SRGB).map(to_byte), color.alpha())
+    }
+
+    #[test]
+    fn named_colors_are_sorted() {
+        assert!(NAMED_COLORS.windows(2).all(|pair| pair[0].0 < pair[1].0));
+    }
+
+    #[test]
+    fn parses_hex() {
+        assert_eq!(parse_bytes("#f80"), ([0xff, 0x88, 0x00], 1f32));
+        assert_eq!(parse_bytes("#f808"), ([0xff, 0x88, 0x00], byte(0x88)));
+        assert_eq!(parse_bytes("#336699"), ([0x33, 0x66, 0x99], 1f32));
+        assert_eq!(parse_bytes("#33669980"), ([0x33, 0x66, 0x99], byte(0x80)));
+    }
+
+    #[test]
+    fn parses_names() {
+        assert_eq!(parse_bytes("rebeccapurple"), ([0x66, 0x33, 0x99], 1f32));
+        assert_eq!(parse_bytes("RebeccaPurple"), ([0x66, 0x33, 0x99], 1f32));
+        assert_eq!(parse_color("transparent").unwrap().alpha(), 0f32);
+    }
+
+    #[test]
+    fn parses_hsl() {
+        let color = parse_color("hsl(200, 80%, 50%)").unwrap();
+        let expected = [0.1f32, 0.6333333f32, 0.9f32];
+        for (component, expected) in color.color().encode(Encoding::SRGB).iter().zip(expected) {
+            assert!((component - expected).abs() < 1e-4, "{} != {}", component, expected);
+        }
+        assert_eq!(color.alpha(), 1f32);
+    }
+
+    #[test]
+    fn reports_malformed_colors() {
+        assert_eq!(parse_color("#12345").unwrap_err().to_string(), "expected 3, 4, 6 or 8 hex digits, found 5");
+        assert_eq!(parse_color("#12g").unwrap_err().to_string(), "invalid hex digit `g` at position 3");
+        assert_eq!(parse_color("rgb(1, 2)").unwrap_err().to_string(), "`rgb` expects 3 or 4 arguments, found 2");
+        assert_eq!(parse_color("bluish").unwrap_err(), ColorParseError::UnknownName("bluish".to_string()));
+    }
+}
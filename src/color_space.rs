@@ -0,0 +1,328 @@
+use serde::{Deserialize, Serialize};
+
+use crate::color::Color;
+use crate::encoding::Encoding;
+use crate::gamut::apply;
+use crate::interpolatable::Interpolation;
+
+/// CIE XYZ of the D65 white point, the reference white of sRGB.
+const WHITE: [f32; 3] = [0.95047, 1.0, 1.08883];
+const LAB_DELTA: f32 = 6f32 / 29f32;
+
+/// Color representations a [Color] can be converted to and interpolated in.
+///
+/// Hues are in degrees, `0.0..360.0`. All other components are unbounded floats,
+/// usually `0.0..=1.0` for RGB-like ones and `0.0..=100.0` for CIE lightness.
+#[derive(Clone, Copy, PartialEq, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorSpace {
+    /// The linear red, green and blue components of [Color].
+    #[default]
+    RGB,
+    /// Hue, saturation, value of the sRGB encoded components, like in color pickers.
+    HSV,
+    /// Hue, saturation, lightness of the sRGB encoded components, like in CSS.
+    HSL,
+    /// CIE 1931 XYZ relative to D65.
+    XYZ,
+    /// CIE L*a*b* relative to D65.
+    LAB,
+    /// Lightness, chroma and hue of CIE L*a*b*.
+    LCH,
+    /// Björn Ottosson's perceptual Oklab.
+    OKLAB,
+    /// Lightness, chroma and hue of Oklab.
+    OKLCH,
+}
+
+impl ColorSpace {
+    /// Components of `color` in this space.
+    pub fn components(self, color: Color) -> [f32; 3] {
+        let rgb = [color.red(), color.green(), color.blue()];
+        match self {
+            ColorSpace::RGB => rgb,
+            ColorSpace::HSV => rgb_to_hsv(color.encode(Encoding::SRGB)),
+            ColorSpace::HSL => rgb_to_hsl(color.encode(Encoding::SRGB)),
+            ColorSpace::XYZ => rgb_to_xyz(rgb),
+            ColorSpace::LAB => xyz_to_lab(rgb_to_xyz(rgb)),
+            ColorSpace::LCH => to_polar(xyz_to_lab(rgb_to_xyz(rgb))),
+            ColorSpace::OKLAB => rgb_to_oklab(rgb),
+            ColorSpace::OKLCH => to_polar(rgb_to_oklab(rgb)),
+        }
+    }
+
+    /// Color with the given components in this space.
+    pub fn color(self, components: [f32; 3]) -> Color {
+        let [red, green, blue] = match self {
+            ColorSpace::RGB => components,
+            ColorSpace::HSV => return Color::decode(Encoding::SRGB, hsv_to_rgb(components)),
+            ColorSpace::HSL => return Color::decode(Encoding::SRGB, hsl_to_rgb(components)),
+            ColorSpace::XYZ => xyz_to_rgb(components),
+            ColorSpace::LAB => xyz_to_rgb(lab_to_xyz(components)),
+            ColorSpace::LCH => xyz_to_rgb(lab_to_xyz(from_polar(components))),
+            ColorSpace::OKLAB => oklab_to_rgb(components),
+            ColorSpace::OKLCH => oklab_to_rgb(from_polar(components)),
+        };
+        Color::new(red, green, blue)
+    }
+
+    /// Index of the hue component and of the component without which the hue is meaningless,
+    /// for cylindrical spaces.
+    fn hue(self) -> Option<(usize, usize)> {
+        match self {
+            ColorSpace::HSV | ColorSpace::HSL => Some((0, 1)),
+            ColorSpace::LCH | ColorSpace::OKLCH => Some((2, 1)),
+            _ => None,
+        }
+    }
+
+    /// Interpolates between `first` and `second` component-wise in this space.
+    ///
+    /// Hues take the shorter way around the color wheel. Gray colors have no hue, so they
+    /// take the hue of the other color.
+    pub fn interpolate(self, interpolation: Interpolation, first: Color, ratio: f64, second: Color) -> Color {
+        let mut first = self.components(first);
+        let mut second = self.components(second);
+
+        if let Some((hue, chroma)) = self.hue() {
+            if first[chroma] <= f32::EPSILON {
+                first[hue] = second[hue];
+            } else if second[chroma] <= f32::EPSILON {
+                second[hue] = first[hue];
+            }
+            let difference = second[hue] - first[hue];
+            if difference > 180f32 {
+                second[hue] -= 360f32;
+            } else if difference < -180f32 {
+                second[hue] += 360f32;
+            }
+        }
+
+        let mut components = [0f32; 3];
+        for (component, (first, second)) in components.iter_mut().zip(first.into_iter().zip(second)) {
+            *component = interpolation.interpolate(first, ratio, second);
+        }
+        if let Some((hue, _)) = self.hue() {
+            components[hue] = components[hue].rem_euclid(360f32);
+        }
+        self.color(components)
+    }
+}
+
+fn rgb_to_hsv([red, green, blue]: [f32; 3]) -> [f32; 3] {
+    let max = red.max(green).max(blue);
+    let min = red.min(green).min(blue);
+    let saturation = if max > 0f32 { (max - min) / max } else { 0f32 };
+    [hue(red, green, blue, max, min), saturation, max]
+}
+
+fn hsv_to_rgb([hue, saturation, value]: [f32; 3]) -> [f32; 3] {
+    let chroma = value * saturation;
+    from_hue(hue, chroma, value - chroma)
+}
+
+fn rgb_to_hsl([red, green, blue]: [f32; 3]) -> [f32; 3] {
+    let max = red.max(green).max(blue);
+    let min = red.min(green).min(blue);
+    let lightness = (max + min) / 2f32;
+    let saturation = if max == min {
+        0f32
+    } else {
+        (max - min) / (1f32 - (2f32 * lightness - 1f32).abs())
+    };
+    [hue(red, green, blue, max, min), saturation, lightness]
+}
+
+fn hsl_to_rgb([hue, saturation, lightness]: [f32; 3]) -> [f32; 3] {
+    let chroma = (1f32 - (2f32 * lightness - 1f32).abs()) * saturation;
+    from_hue(hue, chroma, lightness - chroma / 2f32)
+}
+
+/// Hue shared by HSV and HSL, given the largest and smallest component.
+fn hue(red: f32, green: f32, blue: f32, max: f32, min: f32) -> f32 {
+    let chroma = max - min;
+    let sector = if chroma == 0f32 {
+        0f32
+    } else if max == red {
+        ((green - blue) / chroma).rem_euclid(6f32)
+    } else if max == green {
+        (blue - red) / chroma + 2f32
+    } else {
+        (red - green) / chroma + 4f32
+    };
+    sector * 60f32
+}
+
+/// RGB color with `hue` and `chroma`, lifted by `minimum` on every component.
+fn from_hue(hue: f32, chroma: f32, minimum: f32) -> [f32; 3] {
+    let sector = hue.rem_euclid(360f32) / 60f32;
+    let second = chroma * (1f32 - (sector % 2f32 - 1f32).abs());
+    let [red, green, blue] = match sector as u32 {
+        0 => [chroma, second, 0f32],
+        1 => [second, chroma, 0f32],
+        2 => [0f32, chroma, second],
+        3 => [0f32, second, chroma],
+        4 => [second, 0f32, chroma],
+        _ => [chroma, 0f32, second],
+    };
+    [red + minimum, green + minimum, blue + minimum]
+}
+
+fn rgb_to_xyz(rgb: [f32; 3]) -> [f32; 3] {
+    apply(&[
+        [0.4124564, 0.3575761, 0.1804375],
+        [0.2126729, 0.7151522, 0.0721750],
+        [0.0193339, 0.1191920, 0.9503041],
+    ], rgb)
+}
+
+fn xyz_to_rgb(xyz: [f32; 3]) -> [f32; 3] {
+    apply(&[
+        [3.2404542, -1.5371385, -0.4985314],
+        [-0.9692660, 1.8760108, 0.0415560],
+        [0.0556434, -0.2040259, 1.0572252],
+    ], xyz)
+}
+
+fn xyz_to_lab(xyz: [f32; 3]) -> [f32; 3] {
+    let f = |value: f32, white: f32| {
+        let ratio = value / white;
+        if ratio > LAB_DELTA.powi(3) {
+            ratio.cbrt()
+        } else {
+            ratio / (3f32 * LAB_DELTA * LAB_DELTA) + 4f32 / 29f32
+        }
+    };
+    let [x, y, z] = [f(xyz[0], WHITE[0]), f(xyz[1], WHITE[1]), f(xyz[2], WHITE[2])];
+    [116f32 * y - 16f32, 500f32 * (x - y), 200f32 * (y - z)]
+}
+
+fn lab_to_xyz([lightness, a, b]: [f32; 3]) -> [f32; 3] {
+    let f_inverse = |value: f32, white: f32| {
+        let cube = if value > LAB_DELTA {
+            value.powi(3)
+        } else {
+            3f32 * LAB_DELTA * LAB_DELTA * (value - 4f32 / 29f32)
+        };
+        cube * white
+    };
+    let y = (lightness + 16f32) / 116f32;
+    [f_inverse(y + a / 500f32, WHITE[0]), f_inverse(y, WHITE[1]), f_inverse(y - b / 200f32, WHITE[2])]
+}
+
+fn rgb_to_oklab(rgb: [f32; 3]) -> [f32; 3] {
+    let lms = apply(&[
+        [0.4122214708, 0.5363325363, 0.0514459929],
+        [0.2119034982, 0.6806995451, 0.1073969566],
+        [0.0883024619, 0.2817188376, 0.6299787005],
+    ], rgb);
+    apply(&[
+        [0.2104542553, 0.7936177850, -0.0040720468],
+        [1.9779984951, -2.4285922050, 0.4505937099],
+        [0.0259040371, 0.7827717662, -0.8086757660],
+    ], lms.map(f32::cbrt))
+}
+
+fn oklab_to_rgb(lab: [f32; 3]) -> [f32; 3] {
+    let lms = apply(&[
+        [1f64, 0.3963377774, 0.2158037573],
+        [1f64, -0.1055613458, -0.0638541728],
+        [1f64, -0.0894841775, -1.2914855480],
+    ], lab);
+    apply(&[
+        [4.0767416621, -3.3077115913, 0.2309699292],
+        [-1.2684380046, 2.6097574011, -0.3413193965],
+        [-0.0041960863, -0.7034186147, 1.7076147010],
+    ], lms.map(|value| value.powi(3)))
+}
+
+/// Lightness, chroma and hue of a Lab-like color.
+fn to_polar([lightness, a, b]: [f32; 3]) -> [f32; 3] {
+    [lightness, a.hypot(b), b.atan2(a).to_degrees().rem_euclid(360f32)]
+}
+
+fn from_polar([lightness, chroma, hue]: [f32; 3]) -> [f32; 3] {
+    let (sin, cos) = hue.to_radians().sin_cos();
+    [lightness, chroma * cos, chroma * sin]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SPACES: [ColorSpace; 8] = [
+        ColorSpace::RGB,
+        ColorSpace::HSV,
+        ColorSpace::HSL,
+        ColorSpace::XYZ,
+        ColorSpace::LAB,
+        ColorSpace::LCH,
+        ColorSpace::OKLAB,
+        ColorSpace::OKLCH,
+    ];
+
+    fn assert_close(actual: [f32; 3], expected: [f32; 3], tolerance: f32) {
+        for (actual_component, expected_component) in actual.iter().zip(expected) {
+            assert!((actual_component - expected_component).abs() < tolerance, "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    /// Distance between two hues in degrees, the shorter way around.
+    fn hue_distance(first: f32, second: f32) -> f32 {
+        let difference = (first - second).rem_euclid(360f32);
+        difference.min(360f32 - difference)
+    }
+
+    #[test]
+    fn round_trips() {
+        let colors = [
+            Color::new(1f32, 0f32, 0f32),
+            Color::new(0.2f32, 0.5f32, 0.8f32),
+            Color::new(0.9f32, 0.7f32, 0.05f32),
+            Color::new(0.3f32, 0.3f32, 0.3f32),
+        ];
+        for space in SPACES {
+            for color in colors {
+                let round_trip = space.color(space.components(color));
+                let expected = [color.red(), color.green(), color.blue()];
+                assert_close([round_trip.red(), round_trip.green(), round_trip.blue()], expected, 1e-4);
+            }
+        }
+    }
+
+    #[test]
+    fn red_reference_values() {
+        let red = Color::new(1f32, 0f32, 0f32);
+        assert_close(ColorSpace::HSV.components(red), [0f32, 1f32, 1f32], 1e-6);
+        assert_close(ColorSpace::HSL.components(red), [0f32, 1f32, 0.5f32], 1e-6);
+        assert_close(ColorSpace::XYZ.components(red), [0.4124564f32, 0.2126729f32, 0.0193339f32], 1e-6);
+        assert_close(ColorSpace::LAB.components(red), [53.24f32, 80.09f32, 67.20f32], 0.01);
+        assert_close(ColorSpace::LCH.components(red), [53.24f32, 104.55f32, 40f32], 0.01);
+        assert_close(ColorSpace::OKLAB.components(red), [0.628f32, 0.225f32, 0.126f32], 1e-3);
+        assert_close(ColorSpace::OKLCH.components(red), [0.628f32, 0.258f32, 29.23f32], 0.01);
+    }
+
+    #[test]
+    fn hue_takes_shorter_way() {
+        let first = ColorSpace::HSL.color([350f32, 1f32, 0.5f32]);
+        let second = ColorSpace::HSL.color([10f32, 1f32, 0.5f32]);
+        let middle = ColorSpace::HSL.interpolate(Interpolation::LINEAR, first, 0.5, second);
+        let [hue, saturation, _] = ColorSpace::HSL.components(middle);
+        assert!(hue_distance(hue, 0f32) < 0.01, "{}", hue);
+        assert!((saturation - 1f32).abs() < 1e-4);
+    }
+
+    #[test]
+    fn gray_takes_other_hue() {
+        let gray = Color::new(0.5f32, 0.5f32, 0.5f32);
+        for space in [ColorSpace::HSL, ColorSpace::OKLCH] {
+            let (hue, _) = space.hue().unwrap();
+            let blue = Color::new(0f32, 0f32, 1f32);
+            let blue_hue = space.components(blue)[hue];
+            for ratio in [0.25, 0.5, 0.75] {
+                let color = space.interpolate(Interpolation::LINEAR, gray, ratio, blue);
+                assert!(hue_distance(space.components(color)[hue], blue_hue) < 0.1, "{:?}", space);
+            }
+        }
+    }
+}
@@ -0,0 +1,228 @@
+use crate::alpha_color::AlphaColor;
+use crate::color::Color;
+use crate::color_palette::ColorPalette;
+
+/// Per-sample value a [ColorizerLayer] is driven by.
+///
+/// The first group is defined for escaped samples, the second for samples which did not escape
+/// and [Channel::ATOMDOMAIN] for all of them, so interior layers use it for interior samples only.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Channel {
+    /// Smooth iteration count of escaped samples.
+    ITERATION,
+    /// Estimated distance to the set in samples, for formulas supporting distance estimation.
+    DISTANCE,
+    /// Distance of the orbit to the configured orbit trap.
+    TRAP,
+    /// Argument of the last orbit value of escaped samples, in `0.0..1.0`.
+    ANGLE,
+    /// Binary decomposition, `1.0` where the last orbit value lies in the upper half plane, else `0.0`.
+    BINARY,
+    /// Distance to the nearest of the given number of external field lines per iteration band,
+    /// in units of their spacing, from `0.0` on a line to `0.5` halfway between two.
+    FIELD(u32),
+    /// Like [Channel::FIELD], but also approaching `0.0` on the equipotential lines between
+    /// iteration bands, giving a grid.
+    GRID(u32),
+    /// Average coloring with the given index into the configured average colorings.
+    AVERAGE(usize),
+    /// `|z|` after the last iteration.
+    NORM,
+    /// Smallest `|z|` over the orbit.
+    MINIMUM,
+    /// Length of the attracting cycle, if one was detected.
+    PERIOD,
+    /// Magnitude of the multiplier of the attracting cycle, `0.0` at nuclei and `1.0` at the boundary.
+    MULTIPLIER,
+    /// Estimated distance to the boundary of the set in samples, for formulas supporting
+    /// distance estimation, if enabled by the configuration's
+    /// [interior_distance](crate::escape_time::EscapeTimeConfiguration::interior_distance).
+    INTERIORDISTANCE,
+    /// Atom domain of escaped and interior samples, the iteration at which `|z|` was smallest.
+    /// Within a domain it is the period of the nearest nucleus.
+    ATOMDOMAIN,
+}
+
+/// Function reshaping a channel value before the palette lookup.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Transfer {
+    /// `ln(1 + x)`
+    LOG,
+    SQRT,
+    POWER(f64),
+    OFFSET(f64),
+    SCALE(f64),
+    /// Wraps values into `0.0..period`.
+    CYCLIC(f64),
+}
+
+impl Transfer {
+    pub fn apply(self, value: f64) -> f64 {
+        match self {
+            Transfer::LOG => value.ln_1p(),
+            Transfer::SQRT => value.sqrt(),
+            Transfer::POWER(exponent) => value.powf(exponent),
+            Transfer::OFFSET(offset) => value + offset,
+            Transfer::SCALE(factor) => value * factor,
+            Transfer::CYCLIC(period) => value.rem_euclid(period),
+        }
+    }
+}
+
+/// Combination of a layer color with the color below it, per component.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum BlendMode {
+    NORMAL,
+    MULTIPLY,
+    SCREEN,
+    OVERLAY,
+    ADD,
+}
+
+impl BlendMode {
+    pub fn blend(self, base: Color, layer: Color) -> Color {
+        let component = |base: f32, layer: f32| match self {
+            BlendMode::NORMAL => layer,
+            BlendMode::MULTIPLY => base * layer,
+            BlendMode::SCREEN => 1f32 - (1f32 - base) * (1f32 - layer),
+            BlendMode::OVERLAY => {
+                if base < 0.5f32 {
+                    2f32 * base * layer
+                } else {
+                    1f32 - 2f32 * (1f32 - base) * (1f32 - layer)
+                }
+            }
+            BlendMode::ADD => base + layer,
+        };
+        Color::new(
+            component(base.red(), layer.red()),
+            component(base.green(), layer.green()),
+            component(base.blue(), layer.blue()),
+        )
+    }
+}
+
+pub struct ColorizerLayer {
+    pub channel: Channel,
+    /// Applied to the channel value in order.
+    pub transfers: Vec<Transfer>,
+    pub palette: Box<dyn ColorPalette>,
+    pub blend: BlendMode,
+    /// Opacity of the layer, blending between the color below (`0.0`) and the fully blended color (`1.0`).
+    pub opacity: f32,
+}
+
+impl ColorizerLayer {
+    /// Color of this layer, or `None` if its channel is undefined at the sample.
+    pub fn color(&self, value: Option<f64>) -> Option<Color> {
+        let value = self.transfers.iter().fold(value?, |value, transfer| transfer.apply(value));
+        Some(self.palette.color_at(value))
+    }
+}
+
+/// Stacks of [ColorizerLayer]s blended from bottom to top onto a background,
+/// separately for samples which escaped and for interior ones.
+pub struct Colorizer {
+    pub background: AlphaColor,
+    pub layers: Vec<ColorizerLayer>,
+    pub interior_background: AlphaColor,
+    pub interior_layers: Vec<ColorizerLayer>,
+}
+
+impl Colorizer {
+    /// Colorizer with the given exterior layers, leaving the interior black.
+    pub fn exterior(background: AlphaColor, layers: Vec<ColorizerLayer>) -> Colorizer {
+        Colorizer { background, layers, interior_background: Color::BLACK.into(), interior_layers: Vec::new() }
+    }
+
+    /// Color of a sample, given whether it escaped and the value of each channel at it.
+    pub fn color(&self, escaped: bool, values: impl Fn(Channel) -> Option<f64>) -> AlphaColor {
+        let (background, layers) = if escaped {
+            (self.background, &self.layers)
+        } else {
+            (self.interior_background, &self.interior_layers)
+        };
+
+        layers.iter().fold(background, |base, layer| {
+            match layer.color(values(layer.channel)) {
+                Some(color) => AlphaColor::with_alpha(color, layer.opacity).blend(&base, layer.blend),
+                None => base,
+            }
+        })
+    }
+
+    /// Exterior and interior layers.
+    pub fn all_layers_mut(&mut self) -> impl Iterator<Item=&mut ColorizerLayer> {
+        self.layers.iter_mut().chain(self.interior_layers.iter_mut())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color_palette::{KeyColors, PaletteDefinition};
+    use crate::color_space::ColorSpace;
+    use crate::interpolatable::Interpolation;
+
+    const BASE: Color = Color::new(0.25, 0.5, 0.75);
+    const LAYER: Color = Color::new(0.5, 0.5, 0.5);
+
+    /// Layer on `channel` with a palette from black at `0.0` to white at `1.0`.
+    fn gray_layer(channel: Channel, transfers: Vec<Transfer>) -> ColorizerLayer {
+        let palette = PaletteDefinition {
+            name: None,
+            interpolation: Interpolation::LINEAR,
+            space: ColorSpace::RGB,
+            key_colors: KeyColors::from([(0.0, Color::BLACK), (1.0, Color::WHITE)]),
+        }.scaling();
+        ColorizerLayer { channel, transfers, palette: Box::new(palette), blend: BlendMode::NORMAL, opacity: 1.0 }
+    }
+
+    #[test]
+    fn blend_modes() {
+        assert_eq!(BlendMode::NORMAL.blend(BASE, LAYER), LAYER);
+        assert_eq!(BlendMode::MULTIPLY.blend(BASE, LAYER), Color::new(0.125, 0.25, 0.375));
+        assert_eq!(BlendMode::SCREEN.blend(BASE, LAYER), Color::new(0.625, 0.75, 0.875));
+        // Multiplies below mid gray and screens above it.
+        assert_eq!(BlendMode::OVERLAY.blend(BASE, LAYER), Color::new(0.25, 0.5, 0.75));
+        assert_eq!(BlendMode::OVERLAY.blend(BASE, Color::WHITE), Color::new(0.5, 1.0, 1.0));
+        assert_eq!(BlendMode::ADD.blend(BASE, LAYER), Color::new(0.75, 1.0, 1.25));
+    }
+
+    #[test]
+    fn transfers() {
+        assert!((Transfer::LOG.apply(std::f64::consts::E - 1.0) - 1.0).abs() < 1e-12);
+        assert_eq!(Transfer::SQRT.apply(4.0), 2.0);
+        assert_eq!(Transfer::POWER(3.0).apply(2.0), 8.0);
+        assert_eq!(Transfer::OFFSET(-1.5).apply(2.0), 0.5);
+        assert_eq!(Transfer::SCALE(0.25).apply(2.0), 0.5);
+        assert_eq!(Transfer::CYCLIC(2.0).apply(-0.5), 1.5);
+    }
+
+    #[test]
+    fn layer_applies_transfers_in_order() {
+        let layer = gray_layer(Channel::ITERATION, vec![Transfer::OFFSET(-2.0), Transfer::SCALE(0.25)]);
+        assert_eq!(layer.color(Some(4.0)), Some(Color::new(0.5, 0.5, 0.5)));
+        assert_eq!(layer.color(None), None);
+    }
+
+    #[test]
+    fn layers_blend_onto_background() {
+        let mut top = gray_layer(Channel::DISTANCE, Vec::new());
+        top.blend = BlendMode::MULTIPLY;
+        top.opacity = 0.5;
+        let mut colorizer = Colorizer::exterior(BASE.into(), vec![gray_layer(Channel::ITERATION, Vec::new()), top]);
+        colorizer.interior_background = Color::RED.into();
+
+        let values = |channel: Channel| match channel {
+            Channel::ITERATION => Some(0.5),
+            Channel::DISTANCE => Some(0.5),
+            _ => None,
+        };
+        // Gray `0.5`, then half of it multiplied by gray `0.5`.
+        assert_eq!(colorizer.color(true, values).flatten(), Color::new(0.375, 0.375, 0.375));
+        // Layers without a value leave the color below.
+        assert_eq!(colorizer.color(true, |_| None).flatten(), BASE);
+        assert_eq!(colorizer.color(false, values).flatten(), Color::RED);
+    }
+}
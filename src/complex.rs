@@ -1,4 +1,5 @@
-use std::ops::{Add, AddAssign, Mul, Sub, SubAssign};
+use std::ops::{Add, AddAssign, Div, Mul, Neg, Sub, SubAssign};
+use std::str::FromStr;
 
 use decimal::d128;
 
@@ -12,6 +13,99 @@ pub type ComplexF32 = Complex<f32>;
 pub type ComplexF64 = Complex<f64>;
 pub type ComplexF128 = Complex<d128>;
 
+/// Scalar type usable as component of a [Complex] number.
+///
+/// Transcendental functions are evaluated in `f64` precision for every implementor.
+pub trait Real: Copy + PartialOrd + Add<Output=Self> + Sub<Output=Self> + Mul<Output=Self>
++ Div<Output=Self> + Neg<Output=Self> {
+    fn zero() -> Self;
+    fn one() -> Self;
+    /// Difference between `1.0` and the next larger representable value.
+    fn epsilon() -> Self;
+    fn from_f64(value: f64) -> Self;
+    fn to_f64(self) -> f64;
+}
+
+impl Real for f32 {
+    fn zero() -> Self {
+        0f32
+    }
+
+    fn one() -> Self {
+        1f32
+    }
+
+    fn epsilon() -> Self {
+        f32::EPSILON
+    }
+
+    fn from_f64(value: f64) -> Self {
+        value as f32
+    }
+
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+}
+
+impl Real for f64 {
+    fn zero() -> Self {
+        0f64
+    }
+
+    fn one() -> Self {
+        1f64
+    }
+
+    fn epsilon() -> Self {
+        f64::EPSILON
+    }
+
+    fn from_f64(value: f64) -> Self {
+        value
+    }
+
+    fn to_f64(self) -> f64 {
+        self
+    }
+}
+
+impl Real for d128 {
+    fn zero() -> Self {
+        d128::zero()
+    }
+
+    fn one() -> Self {
+        d128!(1)
+    }
+
+    fn epsilon() -> Self {
+        d128!(1e-33)
+    }
+
+    /// Non-finite values are mapped explicitly, as `f64` and `d128` format them differently.
+    fn from_f64(value: f64) -> Self {
+        if value.is_nan() {
+            d128!(NaN)
+        } else if value.is_infinite() {
+            if value > 0f64 { d128::infinity() } else { d128::neg_infinity() }
+        } else {
+            // Never fails, as d128 parses invalid input as NaN instead of returning an error.
+            d128::from_str(value.to_string().as_str()).unwrap()
+        }
+    }
+
+    fn to_f64(self) -> f64 {
+        if self.is_nan() {
+            f64::NAN
+        } else if self.is_infinite() {
+            if self.is_negative() { f64::NEG_INFINITY } else { f64::INFINITY }
+        } else {
+            f64::from_str(self.to_string().as_str()).unwrap_or(f64::NAN)
+        }
+    }
+}
+
 impl<T> Complex<T> {
     #[inline]
     pub const fn new(re: T, im: T) -> Self {
@@ -19,6 +113,101 @@ impl<T> Complex<T> {
     }
 }
 
+impl<T: Real> Complex<T> {
+    pub fn zero() -> Self {
+        Complex::new(T::zero(), T::zero())
+    }
+
+    pub fn one() -> Self {
+        Complex::new(T::one(), T::zero())
+    }
+
+    pub fn from_f64(value: ComplexF64) -> Self {
+        Complex::new(T::from_f64(value.re), T::from_f64(value.im))
+    }
+
+    pub fn to_f64(self) -> ComplexF64 {
+        Complex::new(self.re.to_f64(), self.im.to_f64())
+    }
+
+    /// Squared absolute value, which is cheaper to calculate than [Complex::norm].
+    pub fn norm_sqr(self) -> T {
+        self.re * self.re + self.im * self.im
+    }
+
+    pub fn norm(self) -> f64 {
+        self.norm_sqr().to_f64().sqrt()
+    }
+
+    pub fn arg(self) -> f64 {
+        self.im.to_f64().atan2(self.re.to_f64())
+    }
+
+    pub fn conj(self) -> Self {
+        Complex::new(self.re, -self.im)
+    }
+
+    pub fn scale(self, factor: T) -> Self {
+        Complex::new(self.re * factor, self.im * factor)
+    }
+
+    /// Raises to an integer power by repeated squaring, staying in the precision of `T`.
+    pub fn powi(self, exponent: i32) -> Self {
+        let mut result = Complex::one();
+        let mut base = self;
+        let mut remaining = exponent.unsigned_abs();
+        while remaining > 0 {
+            if remaining & 1 == 1 {
+                result = result * base;
+            }
+            base = base * base;
+            remaining >>= 1;
+        }
+        if exponent < 0 {
+            Complex::one() / result
+        } else {
+            result
+        }
+    }
+
+    pub fn powc(self, exponent: Self) -> Self {
+        if self.re == T::zero() && self.im == T::zero() {
+            return Complex::zero();
+        }
+        (self.ln() * exponent).exp()
+    }
+
+    pub fn exp(self) -> Self {
+        let re = self.re.to_f64().exp();
+        let im = self.im.to_f64();
+        Complex::from_f64(Complex::new(re * im.cos(), re * im.sin()))
+    }
+
+    pub fn ln(self) -> Self {
+        Complex::from_f64(Complex::new(self.norm().ln(), self.arg()))
+    }
+
+    pub fn sqrt(self) -> Self {
+        self.powc(Complex::from_f64(Complex::new(0.5, 0.0)))
+    }
+
+    pub fn sin(self) -> Self {
+        let value = self.to_f64();
+        Complex::from_f64(Complex::new(
+            value.re.sin() * value.im.cosh(),
+            value.re.cos() * value.im.sinh(),
+        ))
+    }
+
+    pub fn cos(self) -> Self {
+        let value = self.to_f64();
+        Complex::from_f64(Complex::new(
+            value.re.cos() * value.im.cosh(),
+            -value.re.sin() * value.im.sinh(),
+        ))
+    }
+}
+
 impl<T: Add> Add<Complex<T>> for Complex<T> {
     type Output = Complex<T::Output>;
 
@@ -46,6 +235,25 @@ for Complex<T> {
     }
 }
 
+impl<T: Real> Div<Complex<T>> for Complex<T> {
+    type Output = Complex<T>;
+
+    fn div(self, rhs: Complex<T>) -> Self::Output {
+        let denominator = rhs.norm_sqr();
+        let re = (self.re * rhs.re + self.im * rhs.im) / denominator;
+        let im = (self.im * rhs.re - self.re * rhs.im) / denominator;
+        Complex::new(re, im)
+    }
+}
+
+impl<T: Neg> Neg for Complex<T> {
+    type Output = Complex<T::Output>;
+
+    fn neg(self) -> Self::Output {
+        Complex::new(-self.re, -self.im)
+    }
+}
+
 impl<O, T: Add<O, Output=T> + Copy> AddAssign<Complex<O>> for Complex<T> {
     fn add_assign(&mut self, rhs: Complex<O>) {
         self.re = self.re + rhs.re;
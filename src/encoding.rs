@@ -0,0 +1,130 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+/// Transfer function between the linear light stored in [Color](crate::color::Color)
+/// and the non-linear values stored in image files and used by CSS.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Encoding {
+    LINEAR,
+    /// IEC 61966-2-1, the encoding of most 8 and 16 bit images.
+    SRGB,
+    /// ITU-R BT.709 and BT.2020 camera encoding.
+    REC709,
+    /// Pure power law with the given exponent, e.g. `2.2`. Use [Encoding::gamma] to validate it.
+    GAMMA(f32),
+}
+
+impl Encoding {
+    /// Pure power law with `exponent`, which must be positive and finite.
+    pub fn gamma(exponent: f32) -> Result<Encoding, EncodingError> {
+        if exponent > 0f32 && exponent.is_finite() {
+            Ok(Encoding::GAMMA(exponent))
+        } else {
+            Err(EncodingError::InvalidExponent(exponent))
+        }
+    }
+
+    /// Encodes a linear light component.
+    pub fn encode(self, linear: f32) -> f32 {
+        match self {
+            Encoding::LINEAR => linear,
+            Encoding::SRGB => {
+                if linear <= 0.0031308f32 {
+                    12.92f32 * linear
+                } else {
+                    1.055f32 * linear.powf(1f32 / 2.4f32) - 0.055f32
+                }
+            }
+            Encoding::REC709 => {
+                if linear < 0.018f32 {
+                    4.5f32 * linear
+                } else {
+                    1.099f32 * linear.powf(0.45f32) - 0.099f32
+                }
+            }
+            Encoding::GAMMA(gamma) => linear.max(0f32).powf(1f32 / gamma),
+        }
+    }
+
+    /// Decodes a component to linear light, the inverse of [Encoding::encode].
+    pub fn decode(self, encoded: f32) -> f32 {
+        match self {
+            Encoding::LINEAR => encoded,
+            Encoding::SRGB => {
+                if encoded <= 0.04045f32 {
+                    encoded / 12.92f32
+                } else {
+                    ((encoded + 0.055f32) / 1.055f32).powf(2.4f32)
+                }
+            }
+            Encoding::REC709 => {
+                if encoded < 0.081f32 {
+                    encoded / 4.5f32
+                } else {
+                    ((encoded + 0.099f32) / 1.099f32).powf(1f32 / 0.45f32)
+                }
+            }
+            Encoding::GAMMA(gamma) => encoded.max(0f32).powf(gamma),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EncodingError {
+    InvalidExponent(f32),
+}
+
+impl Display for EncodingError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EncodingError::InvalidExponent(exponent) => {
+                write!(f, "gamma exponent must be positive and finite, found {}", exponent)
+            }
+        }
+    }
+}
+
+impl Error for EncodingError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_round_trip(encoding: Encoding, linear: f32) {
+        let decoded = encoding.decode(encoding.encode(linear));
+        assert!((decoded - linear).abs() <= 1e-6f32 + linear * 1e-5f32, "{} != {}", decoded, linear);
+    }
+
+    #[test]
+    fn srgb_round_trips() {
+        for linear in [0f32, 0.0001f32, 0.001f32, 0.003f32, 0.0031308f32, 0.0032f32, 0.18f32, 0.5f32, 1f32] {
+            assert_round_trip(Encoding::SRGB, linear);
+        }
+    }
+
+    #[test]
+    fn srgb_is_linear_near_black() {
+        assert!((Encoding::SRGB.encode(0.001f32) - 0.01292f32).abs() < 1e-7f32);
+        assert!((Encoding::SRGB.decode(0.01292f32) - 0.001f32).abs() < 1e-8f32);
+        assert!((Encoding::SRGB.encode(1f32) - 1f32).abs() < 1e-6f32);
+    }
+
+    #[test]
+    fn gamma_round_trips() {
+        let encoding = Encoding::gamma(2.2f32).unwrap();
+        for linear in [0f32, 0.01f32, 0.5f32, 1f32] {
+            assert_round_trip(encoding, linear);
+        }
+    }
+
+    #[test]
+    fn gamma_rejects_invalid_exponents() {
+        for exponent in [0f32, -2.2f32, f32::INFINITY, f32::NAN] {
+            assert!(Encoding::gamma(exponent).is_err());
+        }
+        assert_eq!(
+            Encoding::gamma(0f32).unwrap_err().to_string(),
+            "gamma exponent must be positive and finite, found 0",
+        );
+    }
+}
@@ -0,0 +1,550 @@
+use image::{Rgb32FImage, Rgba32FImage};
+
+use crate::alpha_color::AlphaColor;
+use crate::average_coloring::{AverageAccumulator, AverageColoring};
+use crate::color::Color;
+use crate::color_palette::ColorPalette;
+use crate::colorizer::{Channel, Colorizer};
+use crate::complex::{Complex, ComplexF64, Real};
+use crate::fractal::{resolve_alpha_samples, resolve_samples, Configuration, Fractal, FractalRepresentation};
+use crate::histogram_equalization::HistogramEqualization;
+use crate::lighting::Lighting;
+use crate::orbit_trap::{OrbitTrap, TrapHit};
+use crate::rendering_settings::RenderingSettings;
+
+/// Iteration rule of an escape-time fractal over `Complex<T>`.
+pub trait EscapeTimeFormula<T: Real>: Clone {
+    /// First value of the orbit belonging to `c`.
+    fn start(&self, _c: Complex<T>) -> Complex<T> {
+        Complex::zero()
+    }
+
+    /// Calculates the value following `z` in the orbit of `c`, where `z` is the result of `iteration` steps.
+    fn step(&self, z: Complex<T>, c: Complex<T>, iteration: u32) -> Complex<T>;
+
+    fn escaped(&self, z: Complex<T>) -> bool;
+
+    /// Derivative of the starting value with respect to the pixel location,
+    /// if the formula supports distance estimation.
+    fn start_derivative(&self) -> Option<Complex<T>> {
+        None
+    }
+
+    /// Derivative of [EscapeTimeFormula::step] with respect to the pixel location,
+    /// given the derivative `derivative` of `z`.
+    fn step_derivative(&self, _z: Complex<T>, derivative: Complex<T>, _iteration: u32) -> Complex<T> {
+        derivative
+    }
+
+    /// Derivative of [EscapeTimeFormula::step] with respect to `z`, if the formula is holomorphic.
+    fn z_derivative(&self, _z: Complex<T>, _iteration: u32) -> Option<Complex<T>> {
+        None
+    }
+
+    /// Second derivatives of [EscapeTimeFormula::step] with respect to `z` twice and to `z` and
+    /// the pixel location, for holomorphic formulas whose orbit depends on the pixel location
+    /// through `c`. Enables the interior distance estimate.
+    fn second_derivatives(&self, _z: Complex<T>, _iteration: u32) -> Option<(Complex<T>, Complex<T>)> {
+        None
+    }
+
+    /// Parameters for continuous iteration counts, if the formula supports them.
+    fn smoothing(&self) -> Option<Smoothing> {
+        None
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct Smoothing {
+    /// Radius of the circle outside of which the orbit counts as escaped.
+    pub bailout: f64,
+    /// Dominant power of `z` in the formula.
+    pub degree: f64,
+}
+
+impl Smoothing {
+    /// Continuous iteration count of an orbit escaping with `z` after `iterations` steps.
+    pub fn smooth_iterations<T: Real>(&self, iterations: u32, z: Complex<T>) -> f64 {
+        let log_ratio = z.norm().ln() / self.bailout.ln();
+        if log_ratio <= 0f64 {
+            return iterations as f64;
+        }
+        iterations as f64 + 1f64 - log_ratio.ln() / self.degree.ln()
+    }
+}
+
+#[derive(Clone)]
+pub struct EscapeTimeConfiguration<T, F> {
+    pub formula: F,
+    pub center: Complex<T>,
+    pub zoom: T,
+    pub max_iterations: u32,
+    pub orbit_trap: Option<OrbitTrap>,
+    /// Average colorings calculated for each sample, stored in the representation in this order.
+    pub average_colorings: Vec<AverageColoring>,
+    /// Calculates the interior distance estimate of samples attracted to a cycle, which takes
+    /// Newton's method over the cycle for each of them.
+    pub interior_distance: bool,
+}
+
+impl<T: Real, F: EscapeTimeFormula<T>> Configuration for EscapeTimeConfiguration<T, F> {}
+
+pub struct EscapeTimeView<T, F> {
+    pub configuration: EscapeTimeConfiguration<T, F>,
+}
+
+impl<T: Real, F: EscapeTimeFormula<T>> Fractal<EscapeTimeConfiguration<T, F>> for EscapeTimeView<T, F> {
+    type Representation = EscapeTimeRepresentation<T, F>;
+
+    fn configuration(&self) -> EscapeTimeConfiguration<T, F> {
+        self.configuration.clone()
+    }
+
+    fn calculate(&self, settings: &RenderingSettings) -> Self::Representation {
+        let configuration = &self.configuration;
+        let smoothing = configuration.formula.smoothing();
+        let sample_step = settings.sample_step(configuration.zoom);
+        // Orbit points closer than a thousandth of a sample count as the same point of a cycle.
+        let period_tolerance = sample_step * sample_step * T::from_f64(1e-6);
+        let mut samples = Vec::with_capacity(settings.sample_count());
+        let mut averages = vec![Vec::with_capacity(settings.sample_count()); configuration.average_colorings.len()];
+
+        for y in 0..settings.sample_height() {
+            for x in 0..settings.sample_width() {
+                let c = settings.sample_location(configuration.center, configuration.zoom, x, y);
+                samples.push(iterate(configuration, c, smoothing, period_tolerance, &mut averages));
+            }
+        }
+
+        EscapeTimeRepresentation {
+            configuration: configuration.clone(),
+            rendering_settings: *settings,
+            sample_step: sample_step.to_f64(),
+            samples,
+            averages,
+        }
+    }
+}
+
+fn iterate<T: Real, F: EscapeTimeFormula<T>>(
+    configuration: &EscapeTimeConfiguration<T, F>,
+    c: Complex<T>,
+    smoothing: Option<Smoothing>,
+    period_tolerance: T,
+    averages: &mut [Vec<f64>],
+) -> EscapeTimeSample {
+    let formula = &configuration.formula;
+    let mut z = formula.start(c);
+    let mut iterations = 0;
+    // The starting point is skipped, it is the same for every pixel of a Mandelbrot-like formula.
+    let mut trap = configuration.orbit_trap.as_ref()
+        .map(|trap| (trap, TrapHit { distance: f64::INFINITY, iteration: 0, position: z.to_f64() }));
+    let mut accumulators: Vec<AverageAccumulator> = configuration.average_colorings.iter()
+        .map(|coloring| AverageAccumulator::new(*coloring, z.to_f64(), c.to_f64()))
+        .collect();
+    let track_orbit = trap.is_some() || !accumulators.is_empty();
+    let mut derivative = formula.start_derivative();
+    // The starting point is skipped like for orbit traps.
+    let mut min_norm_sqr = None;
+    let mut atom_domain = 0;
+    // Brent's cycle detection, comparing against the orbit point at the last power of two.
+    let mut reference = z;
+    let mut reference_iteration = 0;
+    let mut period = None;
+
+    while iterations < configuration.max_iterations && !formula.escaped(z) {
+        if let Some(value) = derivative {
+            derivative = Some(formula.step_derivative(z, value, iterations));
+        }
+        z = formula.step(z, c, iterations);
+        iterations += 1;
+
+        let norm_sqr = z.norm_sqr();
+        if min_norm_sqr.is_none_or(|min_norm_sqr| norm_sqr < min_norm_sqr) {
+            min_norm_sqr = Some(norm_sqr);
+            atom_domain = iterations;
+        }
+        if period.is_none() {
+            if (z - reference).norm_sqr() < period_tolerance {
+                period = Some(iterations - reference_iteration);
+            } else if iterations.is_power_of_two() {
+                reference = z;
+                reference_iteration = iterations;
+            }
+        }
+
+        if track_orbit {
+            let orbit_point = z.to_f64();
+            if let Some((trap, hit)) = &mut trap {
+                trap.record(hit, orbit_point, iterations);
+            }
+            for accumulator in &mut accumulators {
+                accumulator.add(orbit_point);
+            }
+        }
+    }
+
+    let escaped = formula.escaped(z);
+    let smooth_iterations = match smoothing {
+        Some(smoothing) if escaped => smoothing.smooth_iterations(iterations, z),
+        _ => iterations as f64,
+    };
+    let fraction = match smoothing {
+        Some(_) if escaped => Some(smooth_iterations - iterations as f64),
+        _ => None,
+    };
+    for (channel, accumulator) in averages.iter_mut().zip(&accumulators) {
+        channel.push(accumulator.finish(fraction));
+    }
+
+    let final_z = z.to_f64();
+    let distance = match derivative {
+        Some(derivative) if escaped => {
+            let norm = final_z.norm();
+            Some(norm * norm.ln() / derivative.norm())
+        }
+        _ => None,
+    };
+
+    let (period, multiplier, interior_distance) = match period {
+        Some(period) if !escaped => {
+            let (period, multiplier) = cycle(formula, z, c, iterations, period, period_tolerance);
+            let distance = if configuration.interior_distance {
+                interior_distance(formula, z, c, iterations, period)
+            } else {
+                None
+            };
+            (Some(period), multiplier, distance)
+        }
+        _ => (None, None, None),
+    };
+
+    EscapeTimeSample {
+        iterations,
+        smooth_iterations,
+        escaped,
+        final_z,
+        distance,
+        min_norm: min_norm_sqr.map_or(final_z.norm(), |min_norm_sqr| min_norm_sqr.to_f64().sqrt()),
+        atom_domain,
+        period,
+        multiplier,
+        interior_distance,
+        trap: trap.map(|(_, hit)| hit),
+    }
+}
+
+/// Smallest period dividing the detected `period` of the cycle which `z`, reached after `iterations` steps,
+/// is attracted to, together with the magnitude of the cycle's multiplier for holomorphic formulas.
+///
+/// Slowly converging orbits spiral around their cycle, so the detection may find a multiple of the period.
+/// The multiplier is below `1.0` for attracting cycles and `0.0` at their nucleus.
+fn cycle<T: Real, F: EscapeTimeFormula<T>>(
+    formula: &F,
+    start: Complex<T>,
+    c: Complex<T>,
+    iterations: u32,
+    period: u32,
+    tolerance: T,
+) -> (u32, Option<f64>) {
+    let mut z = start;
+    let mut multiplier = Some(Complex::one());
+    for step in 1..=period {
+        let iteration = iterations + step - 1;
+        multiplier = multiplier.zip(formula.z_derivative(z, iteration)).map(|(product, factor)| product * factor);
+        z = formula.step(z, c, iteration);
+        if period.is_multiple_of(step) && (z - start).norm_sqr() < tolerance {
+            return (step, multiplier.map(Complex::norm));
+        }
+    }
+    (period, multiplier.map(Complex::norm))
+}
+
+/// Newton steps locating the cycle point for [interior_distance], which the orbit only approaches.
+const CYCLE_NEWTON_STEPS: u32 = 16;
+
+/// Estimated distance of `c` to the boundary of the set from the attracting cycle of `period`,
+/// which `z` reached after `iterations` steps approaches.
+///
+/// With `F` being `period` steps from the cycle point and `a` its multiplier `∂F/∂z`, the estimate is
+/// `(1 - |a|²) / |∂²F/∂z∂c + ∂²F/∂z² · (∂F/∂c) / (1 - a)|`, which is at least the actual distance
+/// and at most four times it.
+fn interior_distance<T: Real, F: EscapeTimeFormula<T>>(
+    formula: &F,
+    start: Complex<T>,
+    c: Complex<T>,
+    iterations: u32,
+    period: u32,
+) -> Option<f64> {
+    let mut point = start;
+    for _ in 0..CYCLE_NEWTON_STEPS {
+        let mut z = point;
+        let mut z_derivative = Complex::one();
+        for step in 0..period {
+            z_derivative = formula.z_derivative(z, iterations + step)? * z_derivative;
+            z = formula.step(z, c, iterations + step);
+        }
+        point = point - (z - point) / (z_derivative - Complex::one());
+    }
+
+    let mut z = point;
+    let mut z_derivative = Complex::one();
+    let mut c_derivative = Complex::zero();
+    let mut z_second_derivative = Complex::zero();
+    let mut mixed_derivative = Complex::zero();
+    for step in 0..period {
+        let iteration = iterations + step;
+        let first = formula.z_derivative(z, iteration)?;
+        let (second, mixed) = formula.second_derivatives(z, iteration)?;
+        mixed_derivative = second * z_derivative * c_derivative + mixed * z_derivative + first * mixed_derivative;
+        z_second_derivative = second * z_derivative * z_derivative + first * z_second_derivative;
+        c_derivative = formula.step_derivative(z, c_derivative, iteration);
+        z_derivative = first * z_derivative;
+        z = formula.step(z, c, iteration);
+    }
+
+    let denominator = mixed_derivative + z_second_derivative * c_derivative / (Complex::one() - z_derivative);
+    let distance = (1f64 - z_derivative.norm_sqr().to_f64()) / denominator.norm();
+    Some(distance).filter(|distance| distance.is_finite() && *distance >= 0f64)
+}
+
+/// Argument of the last orbit value in `0.0..1.0`.
+fn angle(sample: &EscapeTimeSample) -> f64 {
+    (sample.final_z.arg() / std::f64::consts::TAU).rem_euclid(1f64)
+}
+
+/// Distance of `value` to the nearest integer.
+fn line_distance(value: f64) -> f64 {
+    let fraction = value.rem_euclid(1f64);
+    fraction.min(1f64 - fraction)
+}
+
+#[derive(Clone, Copy)]
+pub struct EscapeTimeSample {
+    pub iterations: u32,
+    pub smooth_iterations: f64,
+    pub escaped: bool,
+    /// Last value of the orbit, in `f64` precision.
+    pub final_z: ComplexF64,
+    /// Estimated distance to the set on the complex plane, for escaped samples of formulas
+    /// supporting it.
+    pub distance: Option<f64>,
+    /// Smallest `|z|` over the orbit, excluding the starting point.
+    pub min_norm: f64,
+    /// Iteration at which `|z|` was smallest, `0` if the orbit escaped right away.
+    pub atom_domain: u32,
+    /// Length of the cycle the orbit of a sample which did not escape was found to be attracted to.
+    pub period: Option<u32>,
+    /// Magnitude of the multiplier of the cycle of `period`, for holomorphic formulas.
+    pub multiplier: Option<f64>,
+    /// Estimated distance to the boundary of the set on the complex plane, for samples with a
+    /// `period` of formulas supporting it.
+    pub interior_distance: Option<f64>,
+    /// Closest approach to the configured orbit trap.
+    pub trap: Option<TrapHit>,
+}
+
+pub struct EscapeTimeRepresentation<T, F> {
+    configuration: EscapeTimeConfiguration<T, F>,
+    rendering_settings: RenderingSettings,
+    /// Distance between neighbouring samples on the complex plane.
+    sample_step: f64,
+    /// Row-major over the sample grid of `rendering_settings`.
+    pub samples: Vec<EscapeTimeSample>,
+    /// One channel per configured average coloring, each row-major over the sample grid.
+    pub averages: Vec<Vec<f64>>,
+}
+
+impl<T, F> EscapeTimeRepresentation<T, F> {
+    /// Value of `channel` at the sample with `index`, if it is defined there.
+    pub fn channel(&self, channel: Channel, index: usize) -> Option<f64> {
+        let sample = &self.samples[index];
+        match channel {
+            Channel::ITERATION => Some(sample.smooth_iterations).filter(|_| sample.escaped),
+            Channel::DISTANCE => sample.distance.map(|distance| distance / self.sample_step),
+            Channel::TRAP => sample.trap.map(|hit| hit.distance),
+            Channel::ANGLE => Some(angle(sample)).filter(|_| sample.escaped),
+            Channel::BINARY => Some(if sample.final_z.im >= 0f64 { 1f64 } else { 0f64 }).filter(|_| sample.escaped),
+            Channel::FIELD(lines) => Some(line_distance(angle(sample) * lines as f64)).filter(|_| sample.escaped),
+            Channel::GRID(lines) => {
+                let field = line_distance(angle(sample) * lines as f64);
+                let equipotential = line_distance(sample.smooth_iterations);
+                Some(field.min(equipotential)).filter(|_| sample.escaped)
+            }
+            Channel::AVERAGE(average) => Some(self.averages[average][index]).filter(|_| sample.escaped),
+            Channel::NORM => Some(sample.final_z.norm()).filter(|_| !sample.escaped),
+            Channel::MINIMUM => Some(sample.min_norm).filter(|_| !sample.escaped),
+            Channel::PERIOD => sample.period.map(|period| period as f64),
+            Channel::MULTIPLIER => sample.multiplier,
+            Channel::INTERIORDISTANCE => sample.interior_distance.map(|distance| distance / self.sample_step),
+            Channel::ATOMDOMAIN => Some(sample.atom_domain as f64),
+        }
+    }
+
+    /// Colors samples by `colorizer`, on black where its backgrounds are transparent.
+    pub fn colorize_layers(&self, colorizer: &Colorizer) -> Rgb32FImage {
+        resolve_samples(&self.rendering_settings, |index| self.layer_color(colorizer, index).flatten())
+    }
+
+    /// Colors samples by `colorizer`, keeping the opacity of its backgrounds and layers.
+    pub fn colorize_layers_alpha(&self, colorizer: &Colorizer) -> Rgba32FImage {
+        resolve_alpha_samples(&self.rendering_settings, |index| self.layer_color(colorizer, index))
+    }
+
+    fn layer_color(&self, colorizer: &Colorizer, index: usize) -> AlphaColor {
+        colorizer.color(self.samples[index].escaped, |channel| self.channel(channel, index))
+    }
+
+    /// Colors samples like [EscapeTimeRepresentation::colorize_layers], lit by `lighting`
+    /// as if the height channel was a surface. Samples without height stay unlit.
+    pub fn colorize_lit(&self, colorizer: &Colorizer, lighting: &Lighting) -> Rgb32FImage {
+        let width = self.rendering_settings.sample_width() as i64;
+        let height = self.rendering_settings.sample_height() as i64;
+        let sample_height = |x: i64, y: i64| {
+            if x < 0 || y < 0 || x >= width || y >= height {
+                None
+            } else {
+                lighting.apply_height_transfers(self.channel(lighting.height, (y * width + x) as usize))
+            }
+        };
+
+        resolve_samples(&self.rendering_settings, |index| {
+            let color = self.layer_color(colorizer, index);
+            let (x, y) = (index as i64 % width, index as i64 / width);
+            if sample_height(x, y).is_none() {
+                return color.flatten();
+            }
+            let heights = [-1, 0, 1].map(|dy| [-1, 0, 1].map(|dx| sample_height(x + dx, y + dy)));
+            AlphaColor::with_alpha(lighting.shade(color.color(), lighting.normal(heights)), color.alpha()).flatten()
+        })
+    }
+
+    /// Colors escaped samples like [FractalRepresentation::colorize], but with smooth iteration
+    /// counts histogram equalized over all escaped samples.
+    pub fn colorize_equalized(&self, palette: &impl ColorPalette) -> Rgb32FImage {
+        let equalization = HistogramEqualization::new(
+            self.samples.iter().filter(|sample| sample.escaped).map(|sample| sample.smooth_iterations)
+        );
+        resolve_samples(&self.rendering_settings, |index| {
+            let sample = &self.samples[index];
+            if sample.escaped {
+                palette.color_at(equalization.equalize(sample.smooth_iterations))
+            } else {
+                Color::BLACK
+            }
+        })
+    }
+
+    /// Colors every sample by the distance of its orbit to the configured orbit trap.
+    ///
+    /// Image traps use their texture where they were hit. Without orbit trap the image is black.
+    pub fn colorize_orbit_trap(&self, palette: &impl ColorPalette) -> Rgb32FImage {
+        resolve_samples(&self.rendering_settings, |index| {
+            match (&self.configuration.orbit_trap, &self.samples[index].trap) {
+                (Some(trap), Some(hit)) => trap.texture_color(hit).unwrap_or_else(|| palette.color_at(hit.distance)),
+                _ => Color::BLACK,
+            }
+        })
+    }
+
+    /// Colors escaped samples by the average coloring stored in `channel`, leaving the rest black.
+    pub fn colorize_average(&self, channel: usize, palette: &impl ColorPalette) -> Rgb32FImage {
+        resolve_samples(&self.rendering_settings, |index| {
+            if self.samples[index].escaped {
+                palette.color_at(self.averages[channel][index])
+            } else {
+                Color::BLACK
+            }
+        })
+    }
+}
+
+impl<T: Real, F> EscapeTimeRepresentation<T, F> {
+    /// Sample containing `location`, if it lies inside the view.
+    pub fn sample_at(&self, location: ComplexF64) -> Option<&EscapeTimeSample> {
+        let center = self.configuration.center.to_f64();
+        let zoom = self.configuration.zoom.to_f64();
+        self.rendering_settings.sample_index(center, zoom, location).map(|index| &self.samples[index])
+    }
+}
+
+impl<T: Real, F: EscapeTimeFormula<T>> FractalRepresentation<EscapeTimeConfiguration<T, F>>
+for EscapeTimeRepresentation<T, F> {
+    fn configuration(&self) -> EscapeTimeConfiguration<T, F> {
+        self.configuration.clone()
+    }
+
+    fn rendering_settings(&self) -> RenderingSettings {
+        self.rendering_settings
+    }
+
+    fn colorize(&self, palette: &impl ColorPalette) -> Rgb32FImage {
+        resolve_samples(&self.rendering_settings, |index| {
+            let sample = &self.samples[index];
+            if sample.escaped {
+                palette.color_at(sample.smooth_iterations)
+            } else {
+                Color::BLACK
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mandelbrot::MandelbrotFormula;
+
+    fn mandelbrot() -> MandelbrotFormula<f64> {
+        MandelbrotFormula { julia: None, bailout: 2f64 }
+    }
+
+    #[test]
+    fn interior_distance_at_main_cardioid_center() {
+        // The estimate at `0` is `1 / 2`, twice the distance to the cusp at `1 / 4`.
+        let distance = interior_distance(&mandelbrot(), Complex::new(1e-3, 0f64), Complex::zero(), 100, 1).unwrap();
+        assert!((distance - 0.5f64).abs() < 1e-9, "{}", distance);
+    }
+
+    #[test]
+    fn interior_distance_at_period_two_center() {
+        // The period 2 component is the disk of radius `1 / 4` around `-1`.
+        let c = Complex::new(-1f64, 0f64);
+        let distance = interior_distance(&mandelbrot(), Complex::new(-1e-3, 0f64), c, 100, 2).unwrap();
+        assert!((distance - 0.25f64).abs() < 1e-9, "{}", distance);
+    }
+
+    #[test]
+    fn interior_distance_within_period_two_disk() {
+        let c = Complex::new(-1.1f64, 0f64);
+        let distance = interior_distance(&mandelbrot(), Complex::new(0f64, 0f64), c, 100, 2).unwrap();
+        assert!((0.15f64..=0.6f64).contains(&distance), "{}", distance);
+    }
+
+    #[test]
+    fn interior_distance_only_if_enabled() {
+        let mut configuration = EscapeTimeConfiguration {
+            formula: mandelbrot(),
+            center: Complex::zero(),
+            zoom: 1f64,
+            max_iterations: 100,
+            orbit_trap: None,
+            average_colorings: Vec::new(),
+            interior_distance: false,
+        };
+        let c = Complex::new(-0.1f64, 0f64);
+        let sample = iterate(&configuration, c, None, 1e-12f64, &mut []);
+        assert_eq!(sample.period, Some(1));
+        assert!(sample.multiplier.is_some());
+        assert!(sample.interior_distance.is_none());
+
+        configuration.interior_distance = true;
+        assert!(iterate(&configuration, c, None, 1e-12f64, &mut []).interior_distance.is_some());
+    }
+
+    #[test]
+    fn no_interior_distance_for_julia_sets() {
+        let formula = MandelbrotFormula { julia: Some(Complex::zero()), bailout: 2f64 };
+        assert!(interior_distance(&formula, Complex::zero(), Complex::zero(), 100, 1).is_none());
+    }
+}
@@ -0,0 +1,576 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::ops::Range;
+
+use crate::complex::{Complex, ComplexF64, Real};
+use crate::escape_time::{EscapeTimeFormula, Smoothing};
+
+/// Escape-time formula parsed from text, e.g. `z = z^3 - 0.5*z + c` with bailout `|z| > 4`.
+///
+/// Formulas may use the variables `z` and `c`, the constants `i`, `pi` and `e`, the operators
+/// `+ - * / ^`, `|x|` for the absolute value and the functions `sin`, `cos`, `exp`, `ln`, `log`,
+/// `sqrt`, `conj`, `re`, `im` and `abs`.
+#[derive(Clone)]
+pub struct ExpressionFormula<T> {
+    iteration: Expression<T>,
+    bailout: Bailout<T>,
+    smoothing: Option<Smoothing>,
+}
+
+impl<T: Real> ExpressionFormula<T> {
+    pub fn parse(formula: &str, bailout: &str) -> Result<Self, FormulaError> {
+        let iteration = Parser::new(formula)?.parse_formula()?;
+        let bailout = Parser::new(bailout)?.parse_bailout()?;
+
+        let smoothing = match (bailout.radius(), iteration.degree()) {
+            (Some(bailout), Some(degree)) if bailout > 1f64 && degree > 1f64 => {
+                Some(Smoothing { bailout, degree })
+            }
+            _ => None,
+        };
+
+        Ok(ExpressionFormula { iteration, bailout, smoothing })
+    }
+}
+
+impl<T: Real> EscapeTimeFormula<T> for ExpressionFormula<T> {
+    fn step(&self, z: Complex<T>, c: Complex<T>, _iteration: u32) -> Complex<T> {
+        self.iteration.evaluate(z, c)
+    }
+
+    fn escaped(&self, z: Complex<T>) -> bool {
+        self.bailout.escaped(z)
+    }
+
+    fn smoothing(&self) -> Option<Smoothing> {
+        self.smoothing
+    }
+}
+
+/// Error in a formula or bailout, pointing at the offending part of the input.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormulaError {
+    pub message: String,
+    pub input: String,
+    /// Byte range of the offending token in `input`.
+    pub span: Range<usize>,
+}
+
+impl Display for FormulaError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let column = self.input[..self.span.start].chars().count();
+        let width = self.input[self.span.clone()].chars().count().max(1);
+        writeln!(f, "{} at column {}", self.message, column + 1)?;
+        writeln!(f, "  {}", self.input)?;
+        write!(f, "  {}{}", " ".repeat(column), "^".repeat(width))
+    }
+}
+
+impl Error for FormulaError {}
+
+#[derive(Clone)]
+enum Expression<T> {
+    Constant(Complex<T>),
+    Z,
+    C,
+    Negate(Box<Expression<T>>),
+    Add(Box<Expression<T>>, Box<Expression<T>>),
+    Subtract(Box<Expression<T>>, Box<Expression<T>>),
+    Multiply(Box<Expression<T>>, Box<Expression<T>>),
+    Divide(Box<Expression<T>>, Box<Expression<T>>),
+    /// Power with a constant integer exponent, calculated exactly.
+    IntegerPower(Box<Expression<T>>, i32),
+    Power(Box<Expression<T>>, Box<Expression<T>>),
+    Function(Function, Box<Expression<T>>),
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Function {
+    SIN,
+    COS,
+    EXP,
+    LN,
+    SQRT,
+    CONJ,
+    RE,
+    IM,
+    ABS,
+}
+
+impl Function {
+    fn from_name(name: &str) -> Option<Function> {
+        match name {
+            "sin" => Some(Function::SIN),
+            "cos" => Some(Function::COS),
+            "exp" => Some(Function::EXP),
+            "ln" | "log" => Some(Function::LN),
+            "sqrt" => Some(Function::SQRT),
+            "conj" => Some(Function::CONJ),
+            "re" => Some(Function::RE),
+            "im" => Some(Function::IM),
+            "abs" => Some(Function::ABS),
+            _ => None,
+        }
+    }
+
+    fn apply<T: Real>(self, value: Complex<T>) -> Complex<T> {
+        match self {
+            Function::SIN => value.sin(),
+            Function::COS => value.cos(),
+            Function::EXP => value.exp(),
+            Function::LN => value.ln(),
+            Function::SQRT => value.sqrt(),
+            Function::CONJ => value.conj(),
+            Function::RE => Complex::new(value.re, T::zero()),
+            Function::IM => Complex::new(value.im, T::zero()),
+            Function::ABS => Complex::new(T::from_f64(value.norm()), T::zero()),
+        }
+    }
+}
+
+impl<T: Real> Expression<T> {
+    fn evaluate(&self, z: Complex<T>, c: Complex<T>) -> Complex<T> {
+        match self {
+            Expression::Constant(value) => *value,
+            Expression::Z => z,
+            Expression::C => c,
+            Expression::Negate(value) => -value.evaluate(z, c),
+            Expression::Add(left, right) => left.evaluate(z, c) + right.evaluate(z, c),
+            Expression::Subtract(left, right) => left.evaluate(z, c) - right.evaluate(z, c),
+            Expression::Multiply(left, right) => left.evaluate(z, c) * right.evaluate(z, c),
+            Expression::Divide(left, right) => left.evaluate(z, c) / right.evaluate(z, c),
+            Expression::IntegerPower(base, exponent) => base.evaluate(z, c).powi(*exponent),
+            Expression::Power(base, exponent) => base.evaluate(z, c).powc(exponent.evaluate(z, c)),
+            Expression::Function(function, value) => function.apply(value.evaluate(z, c)),
+        }
+    }
+
+    /// Dominant power of `z`, if it can be determined.
+    fn degree(&self) -> Option<f64> {
+        match self {
+            Expression::Constant(_) | Expression::C => Some(0f64),
+            Expression::Z => Some(1f64),
+            Expression::Negate(value) => value.degree(),
+            Expression::Add(left, right) | Expression::Subtract(left, right) => {
+                Some(left.degree()?.max(right.degree()?))
+            }
+            Expression::Multiply(left, right) => Some(left.degree()? + right.degree()?),
+            Expression::Divide(left, right) => Some(left.degree()? - right.degree()?),
+            Expression::IntegerPower(base, exponent) => Some(base.degree()? * *exponent as f64),
+            Expression::Power(base, exponent) => match **exponent {
+                Expression::Constant(value) if value.im.to_f64() == 0f64 => {
+                    Some(base.degree()? * value.re.to_f64())
+                }
+                _ => None,
+            },
+            Expression::Function(Function::CONJ | Function::ABS, value) => value.degree(),
+            Expression::Function(..) => None,
+        }
+    }
+
+    fn depends_on_c(&self) -> bool {
+        match self {
+            Expression::Constant(_) | Expression::Z => false,
+            Expression::C => true,
+            Expression::Negate(value) | Expression::IntegerPower(value, _) | Expression::Function(_, value) => {
+                value.depends_on_c()
+            }
+            Expression::Add(left, right)
+            | Expression::Subtract(left, right)
+            | Expression::Multiply(left, right)
+            | Expression::Divide(left, right)
+            | Expression::Power(left, right) => left.depends_on_c() || right.depends_on_c(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Comparison {
+    GT,
+    GE,
+    LT,
+    LE,
+}
+
+/// Escape condition comparing the real parts of two expressions.
+#[derive(Clone)]
+struct Bailout<T> {
+    left: Expression<T>,
+    comparison: Comparison,
+    right: Expression<T>,
+}
+
+impl<T: Real> Bailout<T> {
+    fn escaped(&self, z: Complex<T>) -> bool {
+        let unused = Complex::zero();
+        let left = self.left.evaluate(z, unused).re;
+        let right = self.right.evaluate(z, unused).re;
+        match self.comparison {
+            Comparison::GT => left > right,
+            Comparison::GE => left >= right,
+            Comparison::LT => left < right,
+            Comparison::LE => left <= right,
+        }
+    }
+
+    /// Escape radius for conditions of the form `|z| > r`.
+    fn radius(&self) -> Option<f64> {
+        match (&self.left, self.comparison, &self.right) {
+            (
+                Expression::Function(Function::ABS, value),
+                Comparison::GT | Comparison::GE,
+                Expression::Constant(radius),
+            ) if matches!(**value, Expression::Z) => Some(radius.re.to_f64()),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+enum TokenKind {
+    Number(f64),
+    Identifier(String),
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    Caret,
+    Pipe,
+    OpenParenthesis,
+    CloseParenthesis,
+    Equals,
+    Comparison(Comparison),
+    End,
+}
+
+#[derive(Clone)]
+struct Token {
+    kind: TokenKind,
+    span: Range<usize>,
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, FormulaError> {
+    let mut tokens = Vec::new();
+    let mut characters = input.char_indices().peekable();
+
+    while let Some((start, character)) = characters.next() {
+        let single = |kind| Token { kind, span: start..start + character.len_utf8() };
+        let token = match character {
+            ' ' | '\t' => continue,
+            '+' => single(TokenKind::Plus),
+            '-' => single(TokenKind::Minus),
+            '*' => single(TokenKind::Star),
+            '/' => single(TokenKind::Slash),
+            '^' => single(TokenKind::Caret),
+            '|' => single(TokenKind::Pipe),
+            '(' => single(TokenKind::OpenParenthesis),
+            ')' => single(TokenKind::CloseParenthesis),
+            '=' => single(TokenKind::Equals),
+            '>' | '<' => {
+                let or_equal = matches!(characters.peek(), Some((_, '=')));
+                if or_equal {
+                    characters.next();
+                }
+                let comparison = match (character, or_equal) {
+                    ('>', false) => Comparison::GT,
+                    ('>', true) => Comparison::GE,
+                    ('<', false) => Comparison::LT,
+                    _ => Comparison::LE,
+                };
+                let end = start + if or_equal { 2 } else { 1 };
+                Token { kind: TokenKind::Comparison(comparison), span: start..end }
+            }
+            '0'..='9' | '.' => {
+                let mut end = start + 1;
+                while let Some((index, next)) = characters.peek() {
+                    if next.is_ascii_digit() || *next == '.' {
+                        end = index + 1;
+                        characters.next();
+                    } else {
+                        break;
+                    }
+                }
+                let value = input[start..end].parse::<f64>().map_err(|_| FormulaError {
+                    message: format!("invalid number `{}`", &input[start..end]),
+                    input: input.to_string(),
+                    span: start..end,
+                })?;
+                Token { kind: TokenKind::Number(value), span: start..end }
+            }
+            _ if character.is_alphabetic() => {
+                let mut end = start + character.len_utf8();
+                while let Some((index, next)) = characters.peek() {
+                    if next.is_alphanumeric() || *next == '_' {
+                        end = index + next.len_utf8();
+                        characters.next();
+                    } else {
+                        break;
+                    }
+                }
+                Token { kind: TokenKind::Identifier(input[start..end].to_string()), span: start..end }
+            }
+            _ => {
+                return Err(FormulaError {
+                    message: format!("unexpected character `{}`", character),
+                    input: input.to_string(),
+                    span: start..start + character.len_utf8(),
+                });
+            }
+        };
+        tokens.push(token);
+    }
+
+    tokens.push(Token { kind: TokenKind::End, span: input.len()..input.len() });
+    Ok(tokens)
+}
+
+struct Parser<'a, T> {
+    input: &'a str,
+    tokens: Vec<Token>,
+    position: usize,
+    _real: std::marker::PhantomData<T>,
+}
+
+impl<'a, T: Real> Parser<'a, T> {
+    fn new(input: &'a str) -> Result<Self, FormulaError> {
+        Ok(Parser { input, tokens: tokenize(input)?, position: 0, _real: std::marker::PhantomData })
+    }
+
+    fn peek(&self) -> &Token {
+        &self.tokens[self.position]
+    }
+
+    fn advance(&mut self) -> Token {
+        let token = self.tokens[self.position].clone();
+        if token.kind != TokenKind::End {
+            self.position += 1;
+        }
+        token
+    }
+
+    fn error(&self, message: impl Into<String>, span: Range<usize>) -> FormulaError {
+        FormulaError { message: message.into(), input: self.input.to_string(), span }
+    }
+
+    fn unexpected(&self, token: &Token) -> FormulaError {
+        match &token.kind {
+            TokenKind::End => self.error("unexpected end of input", token.span.clone()),
+            _ => {
+                let text = &self.input[token.span.clone()];
+                self.error(format!("unexpected `{}`", text), token.span.clone())
+            }
+        }
+    }
+
+    fn expect(&mut self, kind: TokenKind, description: &str) -> Result<Token, FormulaError> {
+        let token = self.advance();
+        if token.kind == kind {
+            Ok(token)
+        } else {
+            Err(self.error(format!("expected {}", description), token.span))
+        }
+    }
+
+    fn expect_end(&mut self) -> Result<(), FormulaError> {
+        let token = self.advance();
+        if token.kind == TokenKind::End {
+            Ok(())
+        } else {
+            Err(self.unexpected(&token))
+        }
+    }
+
+    /// `[z =] expression`
+    fn parse_formula(&mut self) -> Result<Expression<T>, FormulaError> {
+        if matches!(&self.peek().kind, TokenKind::Identifier(name) if name == "z")
+            && self.tokens[self.position + 1].kind == TokenKind::Equals {
+            self.position += 2;
+        }
+        let expression = self.parse_expression()?;
+        self.expect_end()?;
+        Ok(expression)
+    }
+
+    /// `expression (> | >= | < | <=) expression`, depending on `z` only.
+    fn parse_bailout(&mut self) -> Result<Bailout<T>, FormulaError> {
+        let start = self.peek().span.start;
+        let left = self.parse_expression()?;
+        let token = self.advance();
+        let comparison = match token.kind {
+            TokenKind::Comparison(comparison) => comparison,
+            _ => return Err(self.error("expected comparison such as `>`", token.span)),
+        };
+        let right = self.parse_expression()?;
+        self.expect_end()?;
+
+        if left.depends_on_c() || right.depends_on_c() {
+            return Err(self.error("bailout may only depend on `z`", start..self.input.len()));
+        }
+        Ok(Bailout { left, comparison, right })
+    }
+
+    fn parse_expression(&mut self) -> Result<Expression<T>, FormulaError> {
+        let mut expression = self.parse_term()?;
+        loop {
+            expression = match self.peek().kind {
+                TokenKind::Plus => {
+                    self.advance();
+                    Expression::Add(Box::new(expression), Box::new(self.parse_term()?))
+                }
+                TokenKind::Minus => {
+                    self.advance();
+                    Expression::Subtract(Box::new(expression), Box::new(self.parse_term()?))
+                }
+                _ => return Ok(expression),
+            };
+        }
+    }
+
+    fn parse_term(&mut self) -> Result<Expression<T>, FormulaError> {
+        let mut expression = self.parse_unary()?;
+        loop {
+            expression = match self.peek().kind {
+                TokenKind::Star => {
+                    self.advance();
+                    Expression::Multiply(Box::new(expression), Box::new(self.parse_unary()?))
+                }
+                TokenKind::Slash => {
+                    self.advance();
+                    Expression::Divide(Box::new(expression), Box::new(self.parse_unary()?))
+                }
+                _ => return Ok(expression),
+            };
+        }
+    }
+
+    fn parse_unary(&mut self) -> Result<Expression<T>, FormulaError> {
+        if self.peek().kind == TokenKind::Minus {
+            self.advance();
+            return Ok(Expression::Negate(Box::new(self.parse_unary()?)));
+        }
+        self.parse_power()
+    }
+
+    /// Right associative, so `z^2^3` is `z^(2^3)`.
+    fn parse_power(&mut self) -> Result<Expression<T>, FormulaError> {
+        let base = self.parse_atom()?;
+        if self.peek().kind != TokenKind::Caret {
+            return Ok(base);
+        }
+        self.advance();
+
+        let exponent = self.parse_unary()?;
+        let integer = match &exponent {
+            Expression::Constant(value) => integer_exponent(value.to_f64()),
+            Expression::Negate(value) => match **value {
+                Expression::Constant(value) => integer_exponent(value.to_f64()).map(|exponent| -exponent),
+                _ => None,
+            },
+            _ => None,
+        };
+        Ok(match integer {
+            Some(integer) => Expression::IntegerPower(Box::new(base), integer),
+            None => Expression::Power(Box::new(base), Box::new(exponent)),
+        })
+    }
+
+    fn parse_atom(&mut self) -> Result<Expression<T>, FormulaError> {
+        let token = self.advance();
+        match token.kind {
+            TokenKind::Number(value) => Ok(Expression::Constant(Complex::new(T::from_f64(value), T::zero()))),
+            TokenKind::OpenParenthesis => {
+                let expression = self.parse_expression()?;
+                self.expect(TokenKind::CloseParenthesis, "`)`")?;
+                Ok(expression)
+            }
+            TokenKind::Pipe => {
+                let expression = self.parse_expression()?;
+                self.expect(TokenKind::Pipe, "closing `|`")?;
+                Ok(Expression::Function(Function::ABS, Box::new(expression)))
+            }
+            TokenKind::Identifier(ref name) => self.parse_identifier(name, token.span.clone()),
+            _ => Err(self.unexpected(&token)),
+        }
+    }
+
+    fn parse_identifier(&mut self, name: &str, span: Range<usize>) -> Result<Expression<T>, FormulaError> {
+        match name {
+            "z" => return Ok(Expression::Z),
+            "c" => return Ok(Expression::C),
+            "i" => return Ok(Expression::Constant(Complex::new(T::zero(), T::one()))),
+            "pi" => return Ok(Expression::Constant(Complex::new(T::from_f64(std::f64::consts::PI), T::zero()))),
+            "e" => return Ok(Expression::Constant(Complex::new(T::from_f64(std::f64::consts::E), T::zero()))),
+            _ => {}
+        }
+
+        let function = Function::from_name(name)
+            .ok_or_else(|| self.error(format!("unknown name `{}`", name), span))?;
+        self.expect(TokenKind::OpenParenthesis, format!("`(` after `{}`", name).as_str())?;
+        let argument = self.parse_expression()?;
+        self.expect(TokenKind::CloseParenthesis, "`)`")?;
+        Ok(Expression::Function(function, Box::new(argument)))
+    }
+}
+
+fn integer_exponent(value: ComplexF64) -> Option<i32> {
+    if value.im == 0f64 && value.re.fract() == 0f64 && value.re.abs() <= i32::MAX as f64 {
+        Some(value.re as i32)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(formula: &str, bailout: &str) -> Result<ExpressionFormula<f64>, FormulaError> {
+        ExpressionFormula::parse(formula, bailout)
+    }
+
+    #[test]
+    fn evaluates_formula() {
+        let formula = parse("z = z^3 - 0.5*z + c", "|z|>4").unwrap();
+        // (1 + i)^3 - 0.5 (1 + i) + 0.5 = (-2 + 2i) - (0.5 + 0.5i) + 0.5
+        let z = formula.step(Complex::new(1f64, 1f64), Complex::new(0.5f64, 0f64), 0);
+        assert!((z.re + 2f64).abs() < 1e-12 && (z.im - 1.5f64).abs() < 1e-12);
+    }
+
+    #[test]
+    fn evaluates_bailout() {
+        let formula = parse("z = z^3 - 0.5*z + c", "|z|>4").unwrap();
+        assert!(formula.escaped(Complex::new(3f64, 3f64)));
+        assert!(!formula.escaped(Complex::new(2f64, 2f64)));
+        assert_eq!(formula.smoothing().map(|smoothing| smoothing.degree), Some(3f64));
+    }
+
+    #[test]
+    fn unknown_identifier_points_at_name() {
+        let error = parse("z = z^3 + foo(z)", "|z|>4").err().unwrap();
+        assert_eq!(error.message, "unknown name `foo`");
+        assert_eq!(error.span, 10..13);
+        assert_eq!(error.to_string(), "unknown name `foo` at column 11\n  z = z^3 + foo(z)\n            ^^^");
+    }
+
+    #[test]
+    fn unexpected_token_points_at_token() {
+        let error = parse("z = z * * c", "|z|>4").err().unwrap();
+        assert_eq!(error.message, "unexpected `*`");
+        assert_eq!(error.span, 8..9);
+        assert!(error.to_string().starts_with("unexpected `*` at column 9\n"));
+    }
+
+    #[test]
+    fn unexpected_character_points_at_character() {
+        let error = parse("z = z · c", "|z|>4").err().unwrap();
+        assert_eq!(error.span, 6..8);
+        assert_eq!(error.to_string(), "unexpected character `·` at column 7\n  z = z · c\n        ^");
+    }
+
+    #[test]
+    fn bailout_rejects_c() {
+        let error = parse("z^2 + c", "|z + c| > 2").err().unwrap();
+        assert_eq!(error.message, "bailout may only depend on `z`");
+        assert_eq!(error.span, 0..11);
+    }
+}
@@ -0,0 +1,355 @@
+use std::f64::consts::PI;
+
+use image::Rgb32FImage;
+use rand::Rng;
+use rand_chacha::ChaCha8Rng;
+
+use crate::color::Color;
+use crate::color_palette::ColorPalette;
+use crate::complex::{Complex, ComplexF64};
+use crate::fractal::{resolve_samples, Configuration, Fractal, FractalRepresentation};
+use crate::rendering_settings::RenderingSettings;
+use crate::sampling::accumulate_parallel;
+
+/// Iterations of the chaos game discarded before plotting, until the point has reached the attractor.
+const SKIPPED_ITERATIONS: u32 = 20;
+
+/// `x' = a * x + b * y + c`, `y' = d * x + e * y + f`
+#[derive(Clone, Copy)]
+pub struct AffineTransform {
+    pub a: f64,
+    pub b: f64,
+    pub c: f64,
+    pub d: f64,
+    pub e: f64,
+    pub f: f64,
+}
+
+impl AffineTransform {
+    pub const IDENTITY: AffineTransform = AffineTransform { a: 1f64, b: 0f64, c: 0f64, d: 0f64, e: 1f64, f: 0f64 };
+
+    pub fn apply(&self, point: ComplexF64) -> ComplexF64 {
+        Complex::new(
+            self.a * point.re + self.b * point.im + self.c,
+            self.d * point.re + self.e * point.im + self.f,
+        )
+    }
+}
+
+/// Nonlinear functions applied after the affine part of a [FlameTransform],
+/// named and defined as in the fractal flame paper by Draves and Reckase.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Variation {
+    LINEAR,
+    SINUSOIDAL,
+    SPHERICAL,
+    SWIRL,
+    HORSESHOE,
+    POLAR,
+    HANDKERCHIEF,
+    HEART,
+    DISC,
+    SPIRAL,
+    HYPERBOLIC,
+    DIAMOND,
+}
+
+impl Variation {
+    pub fn apply(self, point: ComplexF64) -> ComplexF64 {
+        let (x, y) = (point.re, point.im);
+        let r2 = x * x + y * y;
+        let r = r2.sqrt();
+        let theta = x.atan2(y);
+
+        let (x, y) = match self {
+            Variation::LINEAR => (x, y),
+            Variation::SINUSOIDAL => (x.sin(), y.sin()),
+            Variation::SPHERICAL => (x / r2, y / r2),
+            Variation::SWIRL => {
+                let (sin, cos) = r2.sin_cos();
+                (x * sin - y * cos, x * cos + y * sin)
+            }
+            Variation::HORSESHOE => ((x - y) * (x + y) / r, 2f64 * x * y / r),
+            Variation::POLAR => (theta / PI, r - 1f64),
+            Variation::HANDKERCHIEF => (r * (theta + r).sin(), r * (theta - r).cos()),
+            Variation::HEART => (r * (theta * r).sin(), -r * (theta * r).cos()),
+            Variation::DISC => (theta / PI * (PI * r).sin(), theta / PI * (PI * r).cos()),
+            Variation::SPIRAL => ((theta.cos() + r.sin()) / r, (theta.sin() - r.cos()) / r),
+            Variation::HYPERBOLIC => (theta.sin() / r, r * theta.cos()),
+            Variation::DIAMOND => (theta.sin() * r.cos(), theta.cos() * r.sin()),
+        };
+        Complex::new(x, y)
+    }
+}
+
+#[derive(Clone)]
+pub struct FlameTransform {
+    pub affine: AffineTransform,
+    /// Variations with their blending weights, summed up after the affine transform.
+    pub variations: Vec<(Variation, f64)>,
+    pub post: AffineTransform,
+    /// Relative probability of the chaos game picking this transform.
+    pub weight: f64,
+    /// Palette index in `0.0..=1.0` blended into the color of points passing through.
+    pub color: f64,
+}
+
+impl FlameTransform {
+    pub fn apply(&self, point: ComplexF64) -> ComplexF64 {
+        let affine = self.affine.apply(point);
+        let varied = self.variations.iter()
+            .fold(Complex::zero(), |sum, (variation, weight)| sum + variation.apply(affine).scale(*weight));
+        self.post.apply(varied)
+    }
+}
+
+#[derive(Clone)]
+pub struct FlameConfiguration {
+    pub transforms: Vec<FlameTransform>,
+    /// Applied to every plotted point without feeding back into the chaos game.
+    pub final_transform: Option<FlameTransform>,
+    pub center: ComplexF64,
+    pub zoom: f64,
+    /// Number of plotted points.
+    pub samples: u64,
+    pub seed: u64,
+    pub gamma: f64,
+    /// Blends between gamma correcting the density (`1.0`) and each color channel (`0.0`).
+    pub vibrancy: f64,
+}
+
+impl Configuration for FlameConfiguration {}
+
+pub struct FlameView {
+    pub configuration: FlameConfiguration,
+}
+
+impl Fractal<FlameConfiguration> for FlameView {
+    type Representation = FlameRepresentation;
+
+    fn configuration(&self) -> FlameConfiguration {
+        self.configuration.clone()
+    }
+
+    fn calculate(&self, settings: &RenderingSettings) -> Self::Representation {
+        let configuration = &self.configuration;
+        let histogram = accumulate_parallel(
+            configuration.samples,
+            configuration.seed,
+            settings.sample_count() * (size_of::<u64>() + size_of::<f64>()),
+            || Histogram::new(settings.sample_count()),
+            || (),
+            |histogram, _, random, samples| chaos_game(configuration, settings, histogram, random, samples),
+            |histogram, chunk_histogram| histogram.add(&chunk_histogram),
+        );
+
+        FlameRepresentation {
+            configuration: configuration.clone(),
+            rendering_settings: *settings,
+            hits: histogram.hits,
+            colors: histogram.colors,
+        }
+    }
+}
+
+struct Histogram {
+    hits: Vec<u64>,
+    colors: Vec<f64>,
+}
+
+impl Histogram {
+    fn new(size: usize) -> Histogram {
+        Histogram { hits: vec![0; size], colors: vec![0f64; size] }
+    }
+
+    fn add(&mut self, other: &Histogram) {
+        for (hits, other_hits) in self.hits.iter_mut().zip(&other.hits) {
+            *hits += other_hits;
+        }
+        for (color, other_color) in self.colors.iter_mut().zip(&other.colors) {
+            *color += other_color;
+        }
+    }
+}
+
+fn chaos_game(
+    configuration: &FlameConfiguration,
+    settings: &RenderingSettings,
+    histogram: &mut Histogram,
+    random: &mut ChaCha8Rng,
+    samples: u64,
+) {
+    let total_weight: f64 = configuration.transforms.iter().map(|transform| transform.weight).sum();
+    if configuration.transforms.is_empty() || total_weight <= 0f64 {
+        return;
+    }
+
+    let mut point = Complex::new(random.gen_range(-1f64..1f64), random.gen_range(-1f64..1f64));
+    let mut color = random.gen::<f64>();
+
+    for iteration in 0..samples + SKIPPED_ITERATIONS as u64 {
+        let transform = pick_transform(&configuration.transforms, random.gen::<f64>() * total_weight);
+        point = transform.apply(point);
+        color = (color + transform.color) / 2f64;
+
+        if !point.re.is_finite() || !point.im.is_finite() {
+            point = Complex::new(random.gen_range(-1f64..1f64), random.gen_range(-1f64..1f64));
+            continue;
+        }
+        if iteration < SKIPPED_ITERATIONS as u64 {
+            continue;
+        }
+
+        let (plotted, plotted_color) = match &configuration.final_transform {
+            Some(final_transform) => {
+                (final_transform.apply(point), (color + final_transform.color) / 2f64)
+            }
+            None => (point, color),
+        };
+        if let Some(index) = settings.sample_index(configuration.center, configuration.zoom, plotted) {
+            histogram.hits[index] += 1;
+            histogram.colors[index] += plotted_color;
+        }
+    }
+}
+
+fn pick_transform(transforms: &[FlameTransform], mut choice: f64) -> &FlameTransform {
+    for transform in transforms {
+        if choice < transform.weight {
+            return transform;
+        }
+        choice -= transform.weight;
+    }
+    transforms.last().unwrap()
+}
+
+pub struct FlameRepresentation {
+    configuration: FlameConfiguration,
+    rendering_settings: RenderingSettings,
+    /// Number of points plotted per sample, row-major over the sample grid.
+    pub hits: Vec<u64>,
+    /// Sum of palette indices of the points plotted per sample.
+    pub colors: Vec<f64>,
+}
+
+impl FractalRepresentation<FlameConfiguration> for FlameRepresentation {
+    fn configuration(&self) -> FlameConfiguration {
+        self.configuration.clone()
+    }
+
+    fn rendering_settings(&self) -> RenderingSettings {
+        self.rendering_settings
+    }
+
+    /// Colors each sample by its mean palette index in `0.0..=1.0`, scaled by log density.
+    fn colorize(&self, palette: &impl ColorPalette) -> Rgb32FImage {
+        let max_hits = self.hits.iter().cloned().max().unwrap_or(0);
+        let log_max = (1f64 + max_hits as f64).ln();
+        let inverse_gamma = 1f64 / self.configuration.gamma;
+        let vibrancy = self.configuration.vibrancy;
+
+        resolve_samples(&self.rendering_settings, |index| {
+            let hits = self.hits[index];
+            if hits == 0 {
+                return Color::BLACK;
+            }
+
+            let alpha = (1f64 + hits as f64).ln() / log_max;
+            let gamma_alpha = alpha.powf(inverse_gamma);
+            let color = palette.color_at(self.colors[index] / hits as f64);
+            let channel = |value: f32| {
+                let value = value as f64;
+                let vibrant = value * gamma_alpha;
+                let plain = (value * alpha).powf(inverse_gamma);
+                (vibrancy * vibrant + (1f64 - vibrancy) * plain) as f32
+            };
+            Color::new(channel(color.red()), channel(color.green()), channel(color.blue()))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rendering_settings::{MultiSampling, Resolution};
+
+    fn assert_close(actual: ComplexF64, expected: ComplexF64) {
+        assert!((actual - expected).norm() < 1e-12, "{}{:+}i", actual.re, actual.im);
+    }
+
+    /// Sierpinski triangle, each transform halving the distance to one corner.
+    fn sierpinski(seed: u64) -> FlameView {
+        let corner = |c: f64, f: f64, color: f64| FlameTransform {
+            affine: AffineTransform { a: 0.5, b: 0.0, c, d: 0.0, e: 0.5, f },
+            variations: vec![(Variation::LINEAR, 1.0)],
+            post: AffineTransform::IDENTITY,
+            weight: 1.0,
+            color,
+        };
+        FlameView {
+            configuration: FlameConfiguration {
+                transforms: vec![corner(0.0, 0.0, 0.0), corner(0.5, 0.0, 0.5), corner(0.25, 0.5, 1.0)],
+                final_transform: None,
+                center: Complex::new(0.5, 0.5),
+                zoom: 1.0,
+                samples: 100_000,
+                seed,
+                gamma: 2.2,
+                vibrancy: 1.0,
+            },
+        }
+    }
+
+    fn render(view: &FlameView, threads: usize) -> (Vec<u64>, Vec<f64>) {
+        let settings = RenderingSettings {
+            resolution: Resolution { width: 16, height: 16 },
+            sampling: MultiSampling::NONE,
+        };
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(threads).build().unwrap();
+        let representation = pool.install(|| view.calculate(&settings));
+        (representation.hits, representation.colors)
+    }
+
+    #[test]
+    fn variations_at_known_point() {
+        let point = Complex::new(0.5, 0.5);
+        assert_close(Variation::LINEAR.apply(point), point);
+        assert_close(Variation::SINUSOIDAL.apply(point), Complex::new(0.5f64.sin(), 0.5f64.sin()));
+        assert_close(Variation::SPHERICAL.apply(point), Complex::new(1.0, 1.0));
+        assert_close(Variation::HORSESHOE.apply(point), Complex::new(0.0, 0.5f64.sqrt()));
+    }
+
+    #[test]
+    fn transform_applies_affine_variations_and_post() {
+        let transform = FlameTransform {
+            affine: AffineTransform { a: 2.0, b: 0.0, c: 1.0, d: 0.0, e: 1.0, f: -1.0 },
+            variations: vec![(Variation::LINEAR, 0.5), (Variation::SPHERICAL, 0.5)],
+            post: AffineTransform { a: 1.0, b: 1.0, c: 0.0, d: 0.0, e: 1.0, f: 0.0 },
+            weight: 1.0,
+            color: 0.0,
+        };
+        // The affine part maps `(0, 2)` to `(1, 1)`, which the linear variation keeps and the
+        // spherical one halves, giving `(0.75, 0.75)` before the post transform.
+        assert_close(transform.apply(Complex::new(0.0, 2.0)), Complex::new(1.5, 0.75));
+    }
+
+    #[test]
+    fn fixed_seed_is_deterministic() {
+        let view = sierpinski(3);
+        let (hits, colors) = render(&view, 1);
+        assert_eq!(hits.iter().sum::<u64>(), 100_000);
+        assert_eq!((hits.clone(), colors.clone()), render(&view, 4));
+        assert_ne!(hits, render(&sierpinski(4), 1).0);
+    }
+
+    #[test]
+    fn sierpinski_leaves_center_hole_empty() {
+        let (hits, _) = render(&sierpinski(3), 2);
+        // The sample around `(0.5, 0.2)` lies in the central hole of the triangle.
+        let index = RenderingSettings {
+            resolution: Resolution { width: 16, height: 16 },
+            sampling: MultiSampling::NONE,
+        }.sample_index(Complex::new(0.5, 0.5), 1.0, Complex::new(0.5, 0.2)).unwrap();
+        assert_eq!(hits[index], 0);
+    }
+}
@@ -1,12 +1,18 @@
+use image::{ImageBuffer, Pixel, Rgb32FImage, Rgba32FImage};
+
+use crate::alpha_color::AlphaColor;
+use crate::color::Color;
 use crate::color_palette::ColorPalette;
 use crate::rendering_settings::RenderingSettings;
 
 pub trait Configuration {}
 
 pub trait Fractal<C: Configuration> {
+    type Representation: FractalRepresentation<C>;
+
     fn configuration(&self) -> C;
 
-    fn calculate(&self, settings: &RenderingSettings) -> dyn FractalRepresentation<C>;
+    fn calculate(&self, settings: &RenderingSettings) -> Self::Representation;
 }
 
 pub trait FractalRepresentation<C: Configuration> {
@@ -14,4 +20,47 @@ pub trait FractalRepresentation<C: Configuration> {
     fn rendering_settings(&self) -> RenderingSettings;
 
     fn colorize(&self, palette: &impl ColorPalette) -> image::Rgb32FImage;
-}
\ No newline at end of file
+}
+
+/// Builds the final image by averaging the colors of all samples belonging to a pixel.
+///
+/// `sample_color` receives the index of a sample in row-major order over the sample grid.
+pub(crate) fn resolve_samples(
+    settings: &RenderingSettings,
+    sample_color: impl FnMut(usize) -> Color,
+) -> Rgb32FImage {
+    resolve(settings, sample_color, |colors| Color::average(colors).into())
+}
+
+/// Like [resolve_samples], but keeping the opacity of the samples.
+pub(crate) fn resolve_alpha_samples(
+    settings: &RenderingSettings,
+    sample_color: impl FnMut(usize) -> AlphaColor,
+) -> Rgba32FImage {
+    resolve(settings, sample_color, |colors| AlphaColor::average(colors).into())
+}
+
+fn resolve<C, P: Pixel<Subpixel=f32>>(
+    settings: &RenderingSettings,
+    mut sample_color: impl FnMut(usize) -> C,
+    average: impl Fn(&[C]) -> P,
+) -> ImageBuffer<P, Vec<f32>> {
+    let factor = settings.sampling.factor();
+    let sample_width = settings.sample_width() as usize;
+    let mut image = ImageBuffer::new(settings.resolution.width, settings.resolution.height);
+    let mut colors = Vec::with_capacity((factor * factor) as usize);
+
+    for (x, y, pixel) in image.enumerate_pixels_mut() {
+        colors.clear();
+        for sub_y in 0..factor {
+            for sub_x in 0..factor {
+                let sample_x = (x * factor + sub_x) as usize;
+                let sample_y = (y * factor + sub_y) as usize;
+                colors.push(sample_color(sample_y * sample_width + sample_x));
+            }
+        }
+        *pixel = average(&colors);
+    }
+
+    image
+}
@@ -0,0 +1,196 @@
+use std::sync::OnceLock;
+
+use crate::color::Color;
+
+/// CIE xy chromaticities of the red, green and blue primaries and the white point of an RGB space.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Chromaticities {
+    pub red: [f64; 2],
+    pub green: [f64; 2],
+    pub blue: [f64; 2],
+    pub white: [f64; 2],
+}
+
+const D65: [f64; 2] = [0.3127, 0.3290];
+/// White point of the ACES color spaces, close to D60.
+const ACES_WHITE: [f64; 2] = [0.32168, 0.33767];
+
+/// Chromatic adaptation transform of the Bradford model.
+const BRADFORD: [[f64; 3]; 3] = [
+    [0.8951, 0.2664, -0.1614],
+    [-0.7502, 1.7135, 0.0367],
+    [0.0389, -0.0685, 1.0296],
+];
+
+/// Linear RGB working spaces a [Color], which always uses the sRGB primaries, can be converted to.
+///
+/// Colors outside of the sRGB gamut have negative components in [Color] and stay representable,
+/// just like highlights brighter than `1.0`.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Gamut {
+    SRGB,
+    /// DCI-P3 primaries with D65 white, used by most wide-gamut displays.
+    DISPLAYP3,
+    /// ITU-R BT.2020 primaries of HDR television.
+    REC2020,
+    /// ACES AP1 primaries, the scene-linear working space of ACES.
+    ACESCG,
+}
+
+type Matrix = [[f64; 3]; 3];
+
+/// All gamuts, in declaration order.
+const GAMUTS: [Gamut; 4] = [Gamut::SRGB, Gamut::DISPLAYP3, Gamut::REC2020, Gamut::ACESCG];
+
+impl Gamut {
+    pub fn chromaticities(self) -> Chromaticities {
+        match self {
+            Gamut::SRGB => Chromaticities { red: [0.64, 0.33], green: [0.30, 0.60], blue: [0.15, 0.06], white: D65 },
+            Gamut::DISPLAYP3 => {
+                Chromaticities { red: [0.680, 0.320], green: [0.265, 0.690], blue: [0.150, 0.060], white: D65 }
+            }
+            Gamut::REC2020 => {
+                Chromaticities { red: [0.708, 0.292], green: [0.170, 0.797], blue: [0.131, 0.046], white: D65 }
+            }
+            Gamut::ACESCG => {
+                Chromaticities { red: [0.713, 0.293], green: [0.165, 0.830], blue: [0.128, 0.044], white: ACES_WHITE }
+            }
+        }
+    }
+
+    /// Matrix converting linear sRGB to linear components in this gamut, adapting the white point.
+    pub fn matrix_from_srgb(self) -> [[f64; 3]; 3] {
+        self.matrices().0
+    }
+
+    /// Matrix converting linear components in this gamut to linear sRGB.
+    pub fn matrix_to_srgb(self) -> [[f64; 3]; 3] {
+        self.matrices().1
+    }
+
+    /// Conversion matrices from and to sRGB, calculated once for all gamuts.
+    fn matrices(self) -> (Matrix, Matrix) {
+        static MATRICES: OnceLock<[(Matrix, Matrix); 4]> = OnceLock::new();
+        let matrices = MATRICES.get_or_init(|| {
+            GAMUTS.map(|gamut| {
+                // Exact, so colors given in sRGB convert without rounding errors.
+                if gamut == Gamut::SRGB {
+                    let identity = [[1f64, 0f64, 0f64], [0f64, 1f64, 0f64], [0f64, 0f64, 1f64]];
+                    return (identity, identity);
+                }
+                let source = Gamut::SRGB.chromaticities();
+                let target = gamut.chromaticities();
+                let to_xyz = multiply(&adaptation(source.white, target.white), &rgb_to_xyz(&source));
+                let from_srgb = multiply(&invert(&rgb_to_xyz(&target)), &to_xyz);
+                (from_srgb, invert(&from_srgb))
+            })
+        });
+        matrices[self as usize]
+    }
+
+    /// Linear red, green and blue components of `color` in this gamut.
+    pub fn components(self, color: Color) -> [f32; 3] {
+        apply(&self.matrix_from_srgb(), [color.red(), color.green(), color.blue()])
+    }
+
+    /// Color with the given linear components in this gamut.
+    pub fn color(self, components: [f32; 3]) -> Color {
+        let [red, green, blue] = apply(&self.matrix_to_srgb(), components);
+        Color::new(red, green, blue)
+    }
+}
+
+/// Applies `matrix` to a vector of components, calculated in `f64` precision.
+pub(crate) fn apply(matrix: &[[f64; 3]; 3], vector: [f32; 3]) -> [f32; 3] {
+    let [x, y, z] = vector.map(f64::from);
+    matrix.map(|row| (row[0] * x + row[1] * y + row[2] * z) as f32)
+}
+
+/// CIE XYZ of the chromaticity `xy` with a luminance of `1.0`.
+fn xyz([x, y]: [f64; 2]) -> [f64; 3] {
+    [x / y, 1f64, (1f64 - x - y) / y]
+}
+
+fn rgb_to_xyz(chromaticities: &Chromaticities) -> [[f64; 3]; 3] {
+    let [red, green, blue] = [chromaticities.red, chromaticities.green, chromaticities.blue].map(xyz);
+    let primaries = [[red[0], green[0], blue[0]], [red[1], green[1], blue[1]], [red[2], green[2], blue[2]]];
+    // Scales the primaries so that full intensity on all of them gives the white point.
+    let white = xyz(chromaticities.white);
+    let inverse = invert(&primaries);
+    let scale = inverse.map(|row| row[0] * white[0] + row[1] * white[1] + row[2] * white[2]);
+    primaries.map(|row| [row[0] * scale[0], row[1] * scale[1], row[2] * scale[2]])
+}
+
+/// Bradford transform of XYZ seen under `source` white to XYZ seen under `target` white.
+fn adaptation(source: [f64; 2], target: [f64; 2]) -> [[f64; 3]; 3] {
+    let cone = |white: [f64; 2]| {
+        let white = xyz(white);
+        BRADFORD.map(|row| row[0] * white[0] + row[1] * white[1] + row[2] * white[2])
+    };
+    let (source, target) = (cone(source), cone(target));
+    let scale = [
+        [target[0] / source[0], 0f64, 0f64],
+        [0f64, target[1] / source[1], 0f64],
+        [0f64, 0f64, target[2] / source[2]],
+    ];
+    multiply(&invert(&BRADFORD), &multiply(&scale, &BRADFORD))
+}
+
+fn multiply(a: &[[f64; 3]; 3], b: &[[f64; 3]; 3]) -> [[f64; 3]; 3] {
+    let mut product = [[0f64; 3]; 3];
+    for (row, product_row) in product.iter_mut().enumerate() {
+        for (column, value) in product_row.iter_mut().enumerate() {
+            *value = (0..3).map(|k| a[row][k] * b[k][column]).sum();
+        }
+    }
+    product
+}
+
+fn invert(m: &[[f64; 3]; 3]) -> [[f64; 3]; 3] {
+    let cofactor = |row: usize, column: usize| {
+        let (r0, r1) = ((row + 1) % 3, (row + 2) % 3);
+        let (c0, c1) = ((column + 1) % 3, (column + 2) % 3);
+        m[r0][c0] * m[r1][c1] - m[r0][c1] * m[r1][c0]
+    };
+    let determinant = (0..3).map(|column| m[0][column] * cofactor(0, column)).sum::<f64>();
+    let mut inverse = [[0f64; 3]; 3];
+    for (row, inverse_row) in inverse.iter_mut().enumerate() {
+        for (column, value) in inverse_row.iter_mut().enumerate() {
+            *value = cofactor(column, row) / determinant;
+        }
+    }
+    inverse
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: [f32; 3], expected: [f32; 3]) {
+        for (actual, expected) in actual.iter().zip(expected) {
+            assert!((actual - expected).abs() < 1e-5f32, "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn srgb_round_trips_through_display_p3() {
+        for components in [[1f32, 0f32, 0f32], [0.2f32, 0.5f32, 0.9f32], [0f32, 0f32, 0f32], [2f32, 1f32, 0.5f32]] {
+            let color = Color::new(components[0], components[1], components[2]);
+            let round_trip = Gamut::SRGB.components(Gamut::DISPLAYP3.color(Gamut::DISPLAYP3.components(color)));
+            assert_close(round_trip, components);
+        }
+    }
+
+    #[test]
+    fn white_stays_white() {
+        for gamut in GAMUTS {
+            assert_close(gamut.components(Color::WHITE), [1f32, 1f32, 1f32]);
+            assert_close(Gamut::SRGB.components(gamut.color([1f32, 1f32, 1f32])), [1f32, 1f32, 1f32]);
+        }
+    }
+
+    #[test]
+    fn srgb_matrix_is_identity() {
+        assert_close(Gamut::SRGB.components(Color::new(0.1f32, 0.2f32, 0.3f32)), [0.1f32, 0.2f32, 0.3f32]);
+    }
+}
@@ -0,0 +1,81 @@
+/// Maps values to their rank within a distribution, so colors spread evenly over a palette
+/// no matter how the values are distributed.
+///
+/// Ranks are scaled back onto the range of the input values, so palettes set up for the
+/// raw values can be used unchanged.
+pub struct HistogramEqualization {
+    sorted: Vec<f64>,
+}
+
+impl HistogramEqualization {
+    /// Builds the cumulative distribution of all finite `values`.
+    pub fn new(values: impl IntoIterator<Item=f64>) -> HistogramEqualization {
+        let mut sorted: Vec<f64> = values.into_iter().filter(|value| value.is_finite()).collect();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+        HistogramEqualization { sorted }
+    }
+
+    /// Share of the distribution less than or equal to `value`, in `0.0..=1.0`.
+    pub fn rank(&self, value: f64) -> f64 {
+        if self.sorted.is_empty() {
+            return 0f64;
+        }
+        let below = self.sorted.partition_point(|sorted| *sorted <= value);
+        below as f64 / self.sorted.len() as f64
+    }
+
+    /// `value` replaced by its rank, scaled onto the range of the distribution.
+    ///
+    /// The smallest value maps to the minimum and the largest to the maximum, so evenly
+    /// distributed values stay unchanged.
+    pub fn equalize(&self, value: f64) -> f64 {
+        match (self.sorted.first(), self.sorted.last()) {
+            (Some(min), Some(max)) if self.sorted.len() > 1 => {
+                let below = self.sorted.partition_point(|sorted| *sorted <= value);
+                let position = below.saturating_sub(1) as f64 / (self.sorted.len() - 1) as f64;
+                min + position * (max - min)
+            }
+            (Some(min), _) => *min,
+            _ => value,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uniform_distribution_stays_unchanged() {
+        let equalization = HistogramEqualization::new((0..=100).map(|value| value as f64 * 0.5));
+        for value in [0.0, 0.5, 12.5, 25.0, 49.5, 50.0] {
+            assert_eq!(equalization.equalize(value), value);
+        }
+    }
+
+    #[test]
+    fn skewed_distribution_spreads_evenly() {
+        // Most values are small, so they take up most of the range after equalization.
+        let equalization = HistogramEqualization::new([0.0, 1.0, 2.0, 3.0, 100.0]);
+        assert_eq!(equalization.equalize(0.0), 0.0);
+        assert_eq!(equalization.equalize(1.0), 25.0);
+        assert_eq!(equalization.equalize(3.0), 75.0);
+        assert_eq!(equalization.equalize(50.0), 75.0);
+        assert_eq!(equalization.equalize(100.0), 100.0);
+    }
+
+    #[test]
+    fn ranks() {
+        let equalization = HistogramEqualization::new([4.0, 1.0, f64::NAN, 2.0, 3.0, f64::INFINITY]);
+        assert_eq!(equalization.rank(0.0), 0.0);
+        assert_eq!(equalization.rank(2.0), 0.5);
+        assert_eq!(equalization.rank(10.0), 1.0);
+        assert_eq!(equalization.equalize(-1.0), 1.0);
+    }
+
+    #[test]
+    fn degenerate_distributions() {
+        assert_eq!(HistogramEqualization::new([]).equalize(3.0), 3.0);
+        assert_eq!(HistogramEqualization::new([2.0]).equalize(3.0), 2.0);
+    }
+}
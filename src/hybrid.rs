@@ -0,0 +1,316 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+use crate::complex::{Complex, Real};
+use crate::escape_time::{EscapeTimeConfiguration, EscapeTimeFormula, Smoothing};
+
+pub type HybridConfiguration<T> = EscapeTimeConfiguration<T, HybridFormula<T>>;
+
+/// Single iteration of a [HybridFormula].
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum FormulaStep {
+    /// `z^2 + c`
+    MANDELBROT,
+    /// `z^n + c`
+    MULTIBROT(i32),
+    /// `conj(z)^2 + c`
+    TRICORN,
+    /// Burning Ship, `(|re(z)| + i|im(z)|)^2 + c`
+    SHIP,
+    /// `|re(z^2)| + i im(z^2) + c`
+    CELTIC,
+}
+
+impl FormulaStep {
+    pub fn apply<T: Real>(self, z: Complex<T>, c: Complex<T>) -> Complex<T> {
+        match self {
+            FormulaStep::MANDELBROT => z * z + c,
+            FormulaStep::MULTIBROT(power) => z.powi(power) + c,
+            FormulaStep::TRICORN => {
+                let conjugate = z.conj();
+                conjugate * conjugate + c
+            }
+            FormulaStep::SHIP => {
+                let folded = Complex::new(abs(z.re), abs(z.im));
+                folded * folded + c
+            }
+            FormulaStep::CELTIC => {
+                let square = z * z;
+                Complex::new(abs(square.re), square.im) + c
+            }
+        }
+    }
+
+    /// Derivative of [FormulaStep::apply] with respect to `c`, for the holomorphic steps.
+    ///
+    /// `c_derivative` is the derivative of the added `c` itself, `0` for Julia sets.
+    pub fn derivative<T: Real>(
+        self,
+        z: Complex<T>,
+        derivative: Complex<T>,
+        c_derivative: Complex<T>,
+    ) -> Option<Complex<T>> {
+        match self {
+            FormulaStep::MANDELBROT => {
+                let doubled = z * derivative;
+                Some(doubled + doubled + c_derivative)
+            }
+            FormulaStep::MULTIBROT(power) => {
+                let factor = z.powi(power - 1).scale(T::from_f64(power as f64));
+                Some(factor * derivative + c_derivative)
+            }
+            _ => None,
+        }
+    }
+
+    /// Second derivative of [FormulaStep::apply] with respect to `z`, for the holomorphic steps.
+    pub fn second_derivative<T: Real>(self, z: Complex<T>) -> Option<Complex<T>> {
+        match self {
+            FormulaStep::MANDELBROT => Some(Complex::new(T::from_f64(2f64), T::zero())),
+            FormulaStep::MULTIBROT(power) => {
+                Some(z.powi(power - 2).scale(T::from_f64(power as f64 * (power - 1) as f64)))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn degree(self) -> f64 {
+        match self {
+            FormulaStep::MULTIBROT(power) => power.unsigned_abs() as f64,
+            _ => 2f64,
+        }
+    }
+}
+
+fn abs<T: Real>(value: T) -> T {
+    if value < T::zero() {
+        -value
+    } else {
+        value
+    }
+}
+
+/// Escape-time formula alternating between [FormulaStep]s, e.g. two Mandelbrot iterations
+/// followed by one Burning Ship iteration, repeated.
+#[derive(Clone)]
+pub struct HybridFormula<T> {
+    steps: Vec<(FormulaStep, u32)>,
+    repeat_from: usize,
+    /// Iteration at which each step ends, so a step is found without summing all counts.
+    ends: Vec<u64>,
+    /// Iterations before and within the repeated part of the sequence.
+    prefix: u64,
+    cycle: u64,
+    degree: f64,
+    /// Renders the Julia set of this `c` instead, starting the orbit at the pixel location.
+    pub julia: Option<Complex<T>>,
+    pub bailout: T,
+}
+
+impl<T> HybridFormula<T> {
+    /// Applies `steps` in order, each for the given number of consecutive iterations, and
+    /// continues from the step at index `repeat_from` after the last one.
+    ///
+    /// `repeat_from` `0` repeats the whole sequence, so leading steps before it only apply once.
+    pub fn new(
+        steps: Vec<(FormulaStep, u32)>,
+        repeat_from: usize,
+        julia: Option<Complex<T>>,
+        bailout: T,
+    ) -> Result<Self, HybridFormulaError> {
+        if steps.is_empty() {
+            return Err(HybridFormulaError::Empty);
+        }
+        if repeat_from >= steps.len() {
+            return Err(HybridFormulaError::RepeatOutOfRange { repeat_from, steps: steps.len() });
+        }
+        if let Some(index) = steps.iter().position(|(step, _)| *step == FormulaStep::MULTIBROT(0)) {
+            return Err(HybridFormulaError::ConstantStep { index });
+        }
+
+        let ends: Vec<u64> = steps.iter()
+            .scan(0u64, |end, (_, count)| {
+                *end += *count as u64;
+                Some(*end)
+            })
+            .collect();
+        let prefix = match repeat_from {
+            0 => 0,
+            _ => ends[repeat_from - 1],
+        };
+        let cycle = ends[ends.len() - 1] - prefix;
+        if cycle == 0 {
+            return Err(HybridFormulaError::EmptyRepetition);
+        }
+
+        // Average growth per iteration over the repeated part of the sequence.
+        let log_sum: f64 = steps[repeat_from..].iter()
+            .map(|(step, count)| step.degree().ln() * *count as f64)
+            .sum();
+        let degree = (log_sum / cycle as f64).exp();
+
+        Ok(HybridFormula { steps, repeat_from, ends, prefix, cycle, degree, julia, bailout })
+    }
+
+    pub fn steps(&self) -> &[(FormulaStep, u32)] {
+        &self.steps
+    }
+
+    pub fn repeat_from(&self) -> usize {
+        self.repeat_from
+    }
+
+    /// Step applied to calculate iteration `iteration + 1`.
+    pub fn step_at(&self, iteration: u32) -> FormulaStep {
+        let mut position = iteration as u64;
+        if position >= self.prefix + self.cycle {
+            position = self.prefix + (position - self.prefix) % self.cycle;
+        }
+        self.steps[self.ends.partition_point(|end| *end <= position)].0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum HybridFormulaError {
+    Empty,
+    RepeatOutOfRange { repeat_from: usize, steps: usize },
+    /// The repeated steps are all applied for zero iterations.
+    EmptyRepetition,
+    /// `MULTIBROT(0)` ignores `z`, so the orbit never depends on its previous value.
+    ConstantStep { index: usize },
+}
+
+impl Display for HybridFormulaError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HybridFormulaError::Empty => write!(f, "formula must have at least one step"),
+            HybridFormulaError::RepeatOutOfRange { repeat_from, steps } => {
+                write!(f, "cannot repeat from step {} of {} steps", repeat_from + 1, steps)
+            }
+            HybridFormulaError::EmptyRepetition => write!(f, "repeated steps must apply for at least one iteration"),
+            HybridFormulaError::ConstantStep { index } => {
+                write!(f, "step {} is a Multibrot of power 0, which ignores `z`", index + 1)
+            }
+        }
+    }
+}
+
+impl Error for HybridFormulaError {}
+
+impl<T: Real> EscapeTimeFormula<T> for HybridFormula<T> {
+    fn start(&self, c: Complex<T>) -> Complex<T> {
+        match self.julia {
+            Some(_) => c,
+            None => Complex::zero(),
+        }
+    }
+
+    fn step(&self, z: Complex<T>, c: Complex<T>, iteration: u32) -> Complex<T> {
+        self.step_at(iteration).apply(z, self.julia.unwrap_or(c))
+    }
+
+    fn escaped(&self, z: Complex<T>) -> bool {
+        z.norm_sqr() > self.bailout * self.bailout
+    }
+
+    /// Distance estimation is only supported if every step is holomorphic.
+    fn start_derivative(&self) -> Option<Complex<T>> {
+        let holomorphic = self.steps.iter()
+            .all(|(step, _)| matches!(step, FormulaStep::MANDELBROT | FormulaStep::MULTIBROT(_)));
+        match self.julia {
+            _ if !holomorphic => None,
+            Some(_) => Some(Complex::one()),
+            None => Some(Complex::zero()),
+        }
+    }
+
+    fn step_derivative(&self, z: Complex<T>, derivative: Complex<T>, iteration: u32) -> Complex<T> {
+        let c_derivative = match self.julia {
+            Some(_) => Complex::zero(),
+            None => Complex::one(),
+        };
+        self.step_at(iteration).derivative(z, derivative, c_derivative).unwrap_or(derivative)
+    }
+
+    fn z_derivative(&self, z: Complex<T>, iteration: u32) -> Option<Complex<T>> {
+        self.step_at(iteration).derivative(z, Complex::one(), Complex::zero())
+    }
+
+    fn second_derivatives(&self, z: Complex<T>, iteration: u32) -> Option<(Complex<T>, Complex<T>)> {
+        match self.julia {
+            Some(_) => None,
+            None => self.step_at(iteration).second_derivative(z).map(|second| (second, Complex::zero())),
+        }
+    }
+
+    fn smoothing(&self) -> Option<Smoothing> {
+        if self.degree > 1f64 {
+            Some(Smoothing { bailout: self.bailout.to_f64(), degree: self.degree })
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn formula(steps: Vec<(FormulaStep, u32)>, repeat_from: usize) -> Result<HybridFormula<f64>, HybridFormulaError> {
+        HybridFormula::new(steps, repeat_from, None, 2f64)
+    }
+
+    #[test]
+    fn repeats_whole_sequence() {
+        let formula = formula(vec![(FormulaStep::MANDELBROT, 2), (FormulaStep::SHIP, 1)], 0).unwrap();
+        let steps: Vec<FormulaStep> = (0..7).map(|iteration| formula.step_at(iteration)).collect();
+        assert_eq!(steps, [
+            FormulaStep::MANDELBROT, FormulaStep::MANDELBROT, FormulaStep::SHIP,
+            FormulaStep::MANDELBROT, FormulaStep::MANDELBROT, FormulaStep::SHIP,
+            FormulaStep::MANDELBROT,
+        ]);
+    }
+
+    #[test]
+    fn repeats_from_step() {
+        let steps = vec![
+            (FormulaStep::SHIP, 1),
+            (FormulaStep::TRICORN, 0),
+            (FormulaStep::MANDELBROT, 2),
+            (FormulaStep::CELTIC, 1),
+        ];
+        let formula = formula(steps, 1).unwrap();
+        let steps: Vec<FormulaStep> = (0..8).map(|iteration| formula.step_at(iteration)).collect();
+        assert_eq!(steps, [
+            FormulaStep::SHIP,
+            FormulaStep::MANDELBROT, FormulaStep::MANDELBROT, FormulaStep::CELTIC,
+            FormulaStep::MANDELBROT, FormulaStep::MANDELBROT, FormulaStep::CELTIC,
+            FormulaStep::MANDELBROT,
+        ]);
+        assert_eq!(formula.step_at(u32::MAX), FormulaStep::CELTIC);
+    }
+
+    #[test]
+    fn averages_degree_over_repeated_steps() {
+        let steps = vec![(FormulaStep::MULTIBROT(5), 1), (FormulaStep::MANDELBROT, 1), (FormulaStep::MULTIBROT(8), 1)];
+        let formula = formula(steps, 1).unwrap();
+        assert!((formula.smoothing().unwrap().degree - 4f64).abs() < 1e-12);
+    }
+
+    #[test]
+    fn rejects_invalid_steps() {
+        assert_eq!(formula(vec![], 0).err(), Some(HybridFormulaError::Empty));
+        assert_eq!(
+            formula(vec![(FormulaStep::MANDELBROT, 1)], 1).err(),
+            Some(HybridFormulaError::RepeatOutOfRange { repeat_from: 1, steps: 1 }),
+        );
+        assert_eq!(
+            formula(vec![(FormulaStep::MANDELBROT, 1), (FormulaStep::SHIP, 0)], 1).err(),
+            Some(HybridFormulaError::EmptyRepetition),
+        );
+        assert_eq!(
+            formula(vec![(FormulaStep::MANDELBROT, 1), (FormulaStep::MULTIBROT(0), 1)], 0).err(),
+            Some(HybridFormulaError::ConstantStep { index: 1 }),
+        );
+    }
+}
@@ -1,9 +1,13 @@
 use decimal::d128;
+use serde::{Deserialize, Serialize};
 
+use crate::complex::Real;
 use crate::interpolatable::Interpolation::EXPONENTIAL;
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Interpolation {
+    #[default]
     LINEAR,
     CUBIC,
     NEAREST,
@@ -18,7 +22,7 @@ impl Interpolation {
     pub fn interpolate<R, T: Interpolatable<R>>(self, first: T, ratio: f64, second: R) -> T::Output {
         let mut interpolator = self.interpolator();
         interpolator.ratio = ratio;
-        interpolator.interpolate(first, second)
+        interpolator.interpolate(&first, &second)
     }
 }
 
@@ -85,19 +89,15 @@ impl InterpolatablePrimitive<i8> for i8 {
     }
 
     fn interpolate_linear(&self, ratio: f64, other: &i8) -> Self::Output {
-        (other - self) * ratio + self
+        linear(*self as f64, ratio, *other as f64).round() as i8
     }
 
     fn interpolate_cubic(&self, ratio: f64, other: &i8) -> Self::Output {
-        let difference = self - other;
-        2 * difference * ratio * ratio * ratio -
-            3 * difference * ratio * ratio +
-            self
+        linear(*self as f64, cubic_weight(ratio), *other as f64).round() as i8
     }
 
     fn interpolate_exponential(&self, ratio: f64, exponent: f64, other: &i8) -> Self::Output {
-        let a = -1 + self;
-        (ratio.pow(exponent) * ((other - a) as f64).ln()).exp() + a
+        exponential(*self as f64, ratio, exponent, *other as f64).round() as i8
     }
 }
 
@@ -113,19 +113,15 @@ impl InterpolatablePrimitive<i16> for i16 {
     }
 
     fn interpolate_linear(&self, ratio: f64, other: &i16) -> Self::Output {
-        (other - self) * ratio + self
+        linear(*self as f64, ratio, *other as f64).round() as i16
     }
 
     fn interpolate_cubic(&self, ratio: f64, other: &i16) -> Self::Output {
-        let difference = self - other;
-        2 * difference * ratio * ratio * ratio -
-            3 * difference * ratio * ratio +
-            self
+        linear(*self as f64, cubic_weight(ratio), *other as f64).round() as i16
     }
 
     fn interpolate_exponential(&self, ratio: f64, exponent: f64, other: &i16) -> Self::Output {
-        let a = -1 + self;
-        (ratio.pow(exponent) * ((other - a) as f64).ln()).exp() + a
+        exponential(*self as f64, ratio, exponent, *other as f64).round() as i16
     }
 }
 
@@ -141,19 +137,15 @@ impl InterpolatablePrimitive<i32> for i32 {
     }
 
     fn interpolate_linear(&self, ratio: f64, other: &i32) -> Self::Output {
-        (other - self) * ratio + self
+        linear(*self as f64, ratio, *other as f64).round() as i32
     }
 
     fn interpolate_cubic(&self, ratio: f64, other: &i32) -> Self::Output {
-        let difference = self - other;
-        2 * difference * ratio * ratio * ratio -
-            3 * difference * ratio * ratio +
-            self
+        linear(*self as f64, cubic_weight(ratio), *other as f64).round() as i32
     }
 
     fn interpolate_exponential(&self, ratio: f64, exponent: f64, other: &i32) -> Self::Output {
-        let a = -1 + self;
-        (ratio.pow(exponent) * ((other - a) as f64).ln()).exp() + a
+        exponential(*self as f64, ratio, exponent, *other as f64).round() as i32
     }
 }
 
@@ -169,19 +161,15 @@ impl InterpolatablePrimitive<i64> for i64 {
     }
 
     fn interpolate_linear(&self, ratio: f64, other: &i64) -> Self::Output {
-        (other - self) * ratio + self
+        linear(*self as f64, ratio, *other as f64).round() as i64
     }
 
     fn interpolate_cubic(&self, ratio: f64, other: &i64) -> Self::Output {
-        let difference = self - other;
-        2 * difference * ratio * ratio * ratio -
-            3 * difference * ratio * ratio +
-            self
+        linear(*self as f64, cubic_weight(ratio), *other as f64).round() as i64
     }
 
     fn interpolate_exponential(&self, ratio: f64, exponent: f64, other: &i64) -> Self::Output {
-        let a = -1 + self;
-        (ratio.pow(exponent) * ((other - a) as f64).ln()).exp() + a
+        exponential(*self as f64, ratio, exponent, *other as f64).round() as i64
     }
 }
 
@@ -197,19 +185,15 @@ impl InterpolatablePrimitive<i128> for i128 {
     }
 
     fn interpolate_linear(&self, ratio: f64, other: &i128) -> Self::Output {
-        (other - self) * ratio + self
+        linear(*self as f64, ratio, *other as f64).round() as i128
     }
 
     fn interpolate_cubic(&self, ratio: f64, other: &i128) -> Self::Output {
-        let difference = self - other;
-        2 * difference * ratio * ratio * ratio -
-            3 * difference * ratio * ratio +
-            self
+        linear(*self as f64, cubic_weight(ratio), *other as f64).round() as i128
     }
 
     fn interpolate_exponential(&self, ratio: f64, exponent: f64, other: &i128) -> Self::Output {
-        let a = -1 + self;
-        (ratio.pow(exponent) * ((other - a) as f64).ln()).exp() + a
+        exponential(*self as f64, ratio, exponent, *other as f64).round() as i128
     }
 }
 
@@ -225,19 +209,15 @@ impl InterpolatablePrimitive<u8> for u8 {
     }
 
     fn interpolate_linear(&self, ratio: f64, other: &u8) -> Self::Output {
-        (other - self) * ratio + self
+        linear(*self as f64, ratio, *other as f64).round() as u8
     }
 
     fn interpolate_cubic(&self, ratio: f64, other: &u8) -> Self::Output {
-        let difference = self - other;
-        2 * difference * ratio * ratio * ratio -
-            3 * difference * ratio * ratio +
-            self
+        linear(*self as f64, cubic_weight(ratio), *other as f64).round() as u8
     }
 
     fn interpolate_exponential(&self, ratio: f64, exponent: f64, other: &u8) -> Self::Output {
-        let a = -1 + self;
-        (ratio.pow(exponent) * ((other - a) as f64).ln()).exp() + a
+        exponential(*self as f64, ratio, exponent, *other as f64).round() as u8
     }
 }
 
@@ -253,19 +233,15 @@ impl InterpolatablePrimitive<u16> for u16 {
     }
 
     fn interpolate_linear(&self, ratio: f64, other: &u16) -> Self::Output {
-        (other - self) * ratio + self
+        linear(*self as f64, ratio, *other as f64).round() as u16
     }
 
     fn interpolate_cubic(&self, ratio: f64, other: &u16) -> Self::Output {
-        let difference = self - other;
-        2 * difference * ratio * ratio * ratio -
-            3 * difference * ratio * ratio +
-            self
+        linear(*self as f64, cubic_weight(ratio), *other as f64).round() as u16
     }
 
     fn interpolate_exponential(&self, ratio: f64, exponent: f64, other: &u16) -> Self::Output {
-        let a = -1 + self;
-        (ratio.pow(exponent) * ((other - a) as f64).ln()).exp() + a
+        exponential(*self as f64, ratio, exponent, *other as f64).round() as u16
     }
 }
 
@@ -281,19 +257,15 @@ impl InterpolatablePrimitive<u32> for u32 {
     }
 
     fn interpolate_linear(&self, ratio: f64, other: &u32) -> Self::Output {
-        (other - self) * ratio + self
+        linear(*self as f64, ratio, *other as f64).round() as u32
     }
 
     fn interpolate_cubic(&self, ratio: f64, other: &u32) -> Self::Output {
-        let difference = self - other;
-        2 * difference * ratio * ratio * ratio -
-            3 * difference * ratio * ratio +
-            self
+        linear(*self as f64, cubic_weight(ratio), *other as f64).round() as u32
     }
 
     fn interpolate_exponential(&self, ratio: f64, exponent: f64, other: &u32) -> Self::Output {
-        let a = -1 + self;
-        (ratio.pow(exponent) * ((other - a) as f64).ln()).exp() + a
+        exponential(*self as f64, ratio, exponent, *other as f64).round() as u32
     }
 }
 
@@ -309,19 +281,15 @@ impl InterpolatablePrimitive<u64> for u64 {
     }
 
     fn interpolate_linear(&self, ratio: f64, other: &u64) -> Self::Output {
-        (other - self) * ratio + self
+        linear(*self as f64, ratio, *other as f64).round() as u64
     }
 
     fn interpolate_cubic(&self, ratio: f64, other: &u64) -> Self::Output {
-        let difference = self - other;
-        2 * difference * ratio * ratio * ratio -
-            3 * difference * ratio * ratio +
-            self
+        linear(*self as f64, cubic_weight(ratio), *other as f64).round() as u64
     }
 
     fn interpolate_exponential(&self, ratio: f64, exponent: f64, other: &u64) -> Self::Output {
-        let a = -1 + self;
-        (ratio.pow(exponent) * ((other - a) as f64).ln()).exp() + a
+        exponential(*self as f64, ratio, exponent, *other as f64).round() as u64
     }
 }
 
@@ -337,19 +305,15 @@ impl InterpolatablePrimitive<u128> for u128 {
     }
 
     fn interpolate_linear(&self, ratio: f64, other: &u128) -> Self::Output {
-        (other - self) * ratio + self
+        linear(*self as f64, ratio, *other as f64).round() as u128
     }
 
     fn interpolate_cubic(&self, ratio: f64, other: &u128) -> Self::Output {
-        let difference = self - other;
-        2 * difference * ratio * ratio * ratio -
-            3 * difference * ratio * ratio +
-            self
+        linear(*self as f64, cubic_weight(ratio), *other as f64).round() as u128
     }
 
     fn interpolate_exponential(&self, ratio: f64, exponent: f64, other: &u128) -> Self::Output {
-        let a = -1 + self;
-        (ratio.pow(exponent) * ((other - a) as f64).ln()).exp() + a
+        exponential(*self as f64, ratio, exponent, *other as f64).round() as u128
     }
 }
 
@@ -365,19 +329,15 @@ impl InterpolatablePrimitive<f32> for f32 {
     }
 
     fn interpolate_linear(&self, ratio: f64, other: &f32) -> Self::Output {
-        (other - self) * ratio + self
+        linear(*self as f64, ratio, *other as f64) as f32
     }
 
     fn interpolate_cubic(&self, ratio: f64, other: &f32) -> Self::Output {
-        let difference = self - other;
-        2 * difference * ratio * ratio * ratio -
-            3 * difference * ratio * ratio +
-            self
+        linear(*self as f64, cubic_weight(ratio), *other as f64) as f32
     }
 
     fn interpolate_exponential(&self, ratio: f64, exponent: f64, other: &f32) -> Self::Output {
-        let a = -1 + self;
-        (ratio.pow(exponent) * ((other - a) as f64).ln()).exp() + a
+        exponential(*self as f64, ratio, exponent, *other as f64) as f32
     }
 }
 
@@ -393,19 +353,15 @@ impl InterpolatablePrimitive<f64> for f64 {
     }
 
     fn interpolate_linear(&self, ratio: f64, other: &f64) -> Self::Output {
-        (other - self) * ratio + self
+        linear(*self, ratio, *other)
     }
 
     fn interpolate_cubic(&self, ratio: f64, other: &f64) -> Self::Output {
-        let difference = self - other;
-        2 * difference * ratio * ratio * ratio -
-            3 * difference * ratio * ratio +
-            self
+        linear(*self, cubic_weight(ratio), *other)
     }
 
     fn interpolate_exponential(&self, ratio: f64, exponent: f64, other: &f64) -> Self::Output {
-        let a = -1 + self;
-        (ratio.pow(exponent) * ((other - a) as f64).ln()).exp() + a
+        exponential(*self, ratio, exponent, *other)
     }
 }
 
@@ -421,22 +377,36 @@ impl InterpolatablePrimitive<d128> for d128 {
     }
 
     fn interpolate_linear(&self, ratio: f64, other: &d128) -> Self::Output {
-        (other - self) * ratio + self
+        (*other - *self) * d128::from_f64(ratio) + *self
     }
 
     fn interpolate_cubic(&self, ratio: f64, other: &d128) -> Self::Output {
-        let difference = self - other;
-        2 * difference * ratio * ratio * ratio -
-            3 * difference * ratio * ratio +
-            self
+        (*other - *self) * d128::from_f64(cubic_weight(ratio)) + *self
     }
 
+    /// Evaluated in `f64` precision, like all transcendental functions of [Real].
     fn interpolate_exponential(&self, ratio: f64, exponent: f64, other: &d128) -> Self::Output {
-        let a = -1 + self;
-        (ratio.pow(exponent) * ((other - a) as f64).ln()).exp() + a
+        d128::from_f64(exponential(self.to_f64(), ratio, exponent, other.to_f64()))
     }
 }
 
+fn linear(first: f64, ratio: f64, second: f64) -> f64 {
+    (second - first) * ratio + first
+}
+
+/// Weight of the second value for cubic interpolation, which eases out of and into both values.
+fn cubic_weight(ratio: f64) -> f64 {
+    ratio * ratio * (3f64 - 2f64 * ratio)
+}
+
+/// Grows the distance plus one from `first` towards `second` by the power of `ratio^exponent`,
+/// so `first` is reached at `0.0` and `second` at `1.0` in either direction.
+fn exponential(first: f64, ratio: f64, exponent: f64, second: f64) -> f64 {
+    let difference = second - first;
+    let growth = (difference.abs() + 1f64).powf(ratio.powf(exponent)) - 1f64;
+    first + growth.copysign(difference)
+}
+
 impl Interpolatable<i8> for i8 {
     type Output = i8;
 
@@ -541,16 +511,15 @@ impl Interpolatable<d128> for d128 {
     }
 }
 
-pub struct InterpolatableLocation<R, T: Interpolatable<R>> {
+pub struct InterpolatableLocation<T> {
     pub(crate) value: T,
     pub(crate) location: f64,
 }
 
-impl<R, T: Interpolatable<R>> Interpolatable<InterpolatableLocation<R, T>>
-for InterpolatableLocation<R, T> {
+impl<T: Interpolatable<T>> Interpolatable<InterpolatableLocation<T>> for InterpolatableLocation<T> {
     type Output = T::Output;
 
-    fn interpolate(&self, interpolator: &Interpolator, other: &InterpolatableLocation<R, T>) -> Self::Output {
+    fn interpolate(&self, interpolator: &Interpolator, other: &InterpolatableLocation<T>) -> Self::Output {
         let range = other.location - self.location;
         let relative_location = interpolator.ratio - self.location;
 
@@ -565,4 +534,59 @@ for InterpolatableLocation<R, T> {
             interpolator.interpolate(&self.value, &other.value)
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-9, "{} != {}", actual, expected);
+    }
+
+    #[test]
+    fn linear_between_values() {
+        assert_close(linear(2.0, 0.0, 6.0), 2.0);
+        assert_close(linear(2.0, 0.25, 6.0), 3.0);
+        assert_close(linear(2.0, 1.0, 6.0), 6.0);
+        assert_close(linear(6.0, 0.25, 2.0), 5.0);
+    }
+
+    #[test]
+    fn cubic_weight_eases() {
+        assert_close(cubic_weight(0.0), 0.0);
+        assert_close(cubic_weight(0.25), 0.15625);
+        assert_close(cubic_weight(0.5), 0.5);
+        assert_close(cubic_weight(0.75), 0.84375);
+        assert_close(cubic_weight(1.0), 1.0);
+    }
+
+    #[test]
+    fn exponential_reaches_both_values() {
+        assert_close(exponential(1.0, 0.0, 2.0, 10.0), 1.0);
+        assert_close(exponential(1.0, 1.0, 2.0, 10.0), 10.0);
+        assert_close(exponential(10.0, 0.0, 2.0, 1.0), 10.0);
+        assert_close(exponential(10.0, 1.0, 2.0, 1.0), 1.0);
+    }
+
+    #[test]
+    fn exponential_mirrors_descending_values() {
+        assert_close(exponential(0.0, 0.5, 1.0, 8.0), 2.0);
+        assert_close(exponential(8.0, 0.5, 1.0, 0.0), 6.0);
+        assert_close(exponential(-3.0, 0.5, 1.0, -11.0), -5.0);
+    }
+
+    #[test]
+    fn integers_round() {
+        assert_eq!(Interpolation::LINEAR.interpolate(0i32, 0.5, 3i32), 2);
+        assert_eq!(Interpolation::LINEAR.interpolate(3u8, 0.5, 0u8), 2);
+        assert_eq!(Interpolation::CUBIC.interpolate(0i64, 0.25, 64i64), 10);
+    }
+
+    #[test]
+    fn location_rescales_ratio() {
+        let first = InterpolatableLocation { value: 0f64, location: 0.5 };
+        let second = InterpolatableLocation { value: 4f64, location: 1.0 };
+        assert_close(Interpolation::LINEAR.interpolate(first, 0.75, second), 2.0);
+    }
+}
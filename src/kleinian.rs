@@ -0,0 +1,193 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+use crate::complex::{Complex, Real};
+use crate::escape_time::{EscapeTimeConfiguration, EscapeTimeFormula};
+
+pub type KleinianConfiguration<T> = EscapeTimeConfiguration<T, KleinianFormula<T>>;
+
+/// Two generator Kleinian groups whose first generator is the translation `z + 2`.
+#[derive(Clone, Copy)]
+pub enum KleinianGroup<T> {
+    /// Maskit slice, second generator `t + 1 / z`. `t = 2i` gives an Apollonian-like gasket.
+    MASKIT(Complex<T>),
+    /// Riley slice, second generator `z / (c * z + 1)`.
+    RILEY(Complex<T>),
+}
+
+/// The Maskit slice at `t = 2i`.
+impl<T: Real> Default for KleinianGroup<T> {
+    fn default() -> Self {
+        KleinianGroup::MASKIT(Complex::new(T::zero(), T::from_f64(2f64)))
+    }
+}
+
+/// Limit set of a [KleinianGroup], rendered by repeatedly mapping each point towards the
+/// fundamental domain of the group. The number of mappings needed is the escape time,
+/// points of the limit set never arrive.
+///
+/// The pixel location is the starting point of the orbit, `c` is ignored.
+#[derive(Clone)]
+pub struct KleinianFormula<T> {
+    group: KleinianGroup<T>,
+    /// Möbius transformation `(p * z + q) / (r * z + s)` of the second generator.
+    p: Complex<T>,
+    q: Complex<T>,
+    r: Complex<T>,
+    s: Complex<T>,
+}
+
+impl<T: Real> KleinianFormula<T> {
+    pub fn new(group: KleinianGroup<T>) -> Result<Self, KleinianGroupError> {
+        let zero = Complex::zero();
+        let one = Complex::one();
+        let formula = match group {
+            KleinianGroup::MASKIT(t) => KleinianFormula { group, p: t, q: one, r: one, s: zero },
+            KleinianGroup::RILEY(c) if c.norm_sqr() == T::zero() => return Err(KleinianGroupError::IdentityGenerator),
+            KleinianGroup::RILEY(c) => KleinianFormula { group, p: one, q: zero, r: c, s: one },
+        };
+        match group {
+            KleinianGroup::MASKIT(parameter) | KleinianGroup::RILEY(parameter) if !parameter.norm().is_finite() => {
+                Err(KleinianGroupError::NonFinite)
+            }
+            _ => Ok(formula),
+        }
+    }
+
+    pub fn group(&self) -> KleinianGroup<T> {
+        self.group
+    }
+
+    /// Applies the translation generator until the real part lies within `-1.0..1.0`.
+    fn wrap(z: Complex<T>) -> Complex<T> {
+        let shift = ((z.re.to_f64() + 1f64) / 2f64).floor() * 2f64;
+        Complex::new(z.re - T::from_f64(shift), z.im)
+    }
+
+    /// Inside the isometric circle of the generator, which it maps outwards.
+    fn inside_generator_circle(&self, z: Complex<T>) -> bool {
+        (self.r * z + self.s).norm_sqr() < T::one()
+    }
+
+    /// Inside the isometric circle of the inverse generator, which it maps outwards.
+    fn inside_inverse_circle(&self, z: Complex<T>) -> bool {
+        (self.r * z - self.p).norm_sqr() < T::one()
+    }
+}
+
+impl<T: Real> EscapeTimeFormula<T> for KleinianFormula<T> {
+    fn start(&self, c: Complex<T>) -> Complex<T> {
+        c
+    }
+
+    fn step(&self, z: Complex<T>, _c: Complex<T>, _iteration: u32) -> Complex<T> {
+        let z = Self::wrap(z);
+        if self.inside_generator_circle(z) {
+            (self.p * z + self.q) / (self.r * z + self.s)
+        } else {
+            (self.s * z - self.q) / (self.p - self.r * z)
+        }
+    }
+
+    /// The fundamental domain lies between the translation's strip and outside of both isometric circles.
+    ///
+    /// The poles of the generators at the circle centers count as escaped, as they map to `∞`,
+    /// which lies in the fundamental domain, instead of dividing by zero in the next step.
+    fn escaped(&self, z: Complex<T>) -> bool {
+        let z = Self::wrap(z);
+        let at_pole = (self.r * z + self.s).norm_sqr() == T::zero() || (self.r * z - self.p).norm_sqr() == T::zero();
+        at_pole || (!self.inside_generator_circle(z) && !self.inside_inverse_circle(z))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum KleinianGroupError {
+    NonFinite,
+    /// The Riley slice at `c = 0`, whose second generator is the identity.
+    IdentityGenerator,
+}
+
+impl Display for KleinianGroupError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KleinianGroupError::NonFinite => write!(f, "group parameter must be finite"),
+            KleinianGroupError::IdentityGenerator => write!(f, "Riley parameter must not be 0"),
+        }
+    }
+}
+
+impl Error for KleinianGroupError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Iterations until the orbit starting at `c` escapes, together with its last value.
+    fn escape(formula: &KleinianFormula<f64>, c: Complex<f64>) -> Option<(u32, Complex<f64>)> {
+        let mut z = formula.start(c);
+        for iteration in 0..1000 {
+            if formula.escaped(z) {
+                return Some((iteration, z));
+            }
+            z = formula.step(z, c, iteration);
+        }
+        None
+    }
+
+    fn escapes(formula: &KleinianFormula<f64>, c: Complex<f64>) -> bool {
+        escape(formula, c).is_some()
+    }
+
+    #[test]
+    fn maskit_escapes_in_fundamental_domain() {
+        let formula = KleinianFormula::new(KleinianGroup::default()).unwrap();
+        // Outside of both `|z| < 1` and `|z - 2i| < 1`.
+        assert_eq!(escape(&formula, Complex::new(0.9f64, 1f64)).map(|(iterations, _)| iterations), Some(0));
+        // `2i + 1 / 0.3i` is about `-1.33i`.
+        assert_eq!(escape(&formula, Complex::new(0f64, 0.3f64)).map(|(iterations, _)| iterations), Some(1));
+    }
+
+    #[test]
+    fn maskit_pole_escapes_without_nan() {
+        let formula = KleinianFormula::new(KleinianGroup::default()).unwrap();
+        // `0.5i` maps to the pole `0` of `2i + 1 / z`.
+        for c in [Complex::new(0f64, 0.5f64), Complex::zero(), Complex::new(0f64, 2f64)] {
+            let (iterations, z) = escape(&formula, c).unwrap();
+            assert!(iterations <= 1);
+            assert!(z.re.is_finite() && z.im.is_finite());
+        }
+    }
+
+    #[test]
+    fn maskit_does_not_escape_near_parabolic_fixed_point() {
+        // `i` is the parabolic fixed point of `2i + 1 / z`, which only moves `i + δi` by about
+        // `δ²` per iteration.
+        let formula = KleinianFormula::new(KleinianGroup::default()).unwrap();
+        assert!(!escapes(&formula, Complex::new(0f64, 1f64 + 1e-9)));
+    }
+
+    #[test]
+    fn riley_escapes_in_fundamental_domain() {
+        let formula = KleinianFormula::new(KleinianGroup::RILEY(Complex::new(0f64, 2.5f64))).unwrap();
+        assert!(escapes(&formula, Complex::new(0.9f64, 0f64)));
+    }
+
+    #[test]
+    fn riley_does_not_escape_near_parabolic_fixed_point() {
+        // `0` is the parabolic fixed point of `z / (c * z + 1)`, which only changes `1 / z` by `c`.
+        let formula = KleinianFormula::new(KleinianGroup::RILEY(Complex::new(0f64, 2.5f64))).unwrap();
+        assert!(!escapes(&formula, Complex::new(0f64, 1e-9)));
+    }
+
+    #[test]
+    fn rejects_degenerate_groups() {
+        assert_eq!(
+            KleinianFormula::<f64>::new(KleinianGroup::RILEY(Complex::zero())).err(),
+            Some(KleinianGroupError::IdentityGenerator),
+        );
+        assert_eq!(
+            KleinianFormula::<f64>::new(KleinianGroup::MASKIT(Complex::new(f64::NAN, 2f64))).err(),
+            Some(KleinianGroupError::NonFinite),
+        );
+    }
+}
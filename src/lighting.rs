@@ -0,0 +1,146 @@
+use crate::color::Color;
+use crate::colorizer::{Channel, Transfer};
+
+/// Lambert and Blinn-Phong shading of a height field derived from a representation channel,
+/// giving 2D fractals an embossed look.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Lighting {
+    /// Channel used as height, usually [Channel::DISTANCE] or [Channel::ITERATION].
+    pub height: Channel,
+    /// Applied to the height in order, e.g. [Transfer::LOG] to flatten distance estimates.
+    pub height_transfers: Vec<Transfer>,
+    /// Multiplies the slopes of the height field, steepening the relief.
+    pub height_scale: f64,
+    /// Direction the light comes from in radians, counterclockwise from the right of the image.
+    pub azimuth: f64,
+    /// Angle of the light above the image plane in radians.
+    pub elevation: f64,
+    /// Part of the color which is lit regardless of the surface normal.
+    pub ambient: f32,
+    pub diffuse: f32,
+    pub specular: f32,
+    /// Blinn-Phong exponent, larger values give smaller highlights.
+    pub shininess: f64,
+}
+
+impl Lighting {
+    /// Height of a sample, given the value of the height channel at it.
+    pub(crate) fn apply_height_transfers(&self, value: Option<f64>) -> Option<f64> {
+        Some(self.height_transfers.iter().fold(value?, |value, transfer| transfer.apply(value)))
+    }
+
+    /// Unit surface normal at the center of a 3x3 neighbourhood of heights, `heights[row][column]`
+    /// with rows running downwards. Missing heights fall back to one-sided differences.
+    pub(crate) fn normal(&self, heights: [[Option<f64>; 3]; 3]) -> [f64; 3] {
+        let center = heights[1][1];
+        let slope = |before: Option<f64>, after: Option<f64>| match (before, after, center) {
+            (Some(before), Some(after), _) => (after - before) / 2f64,
+            (Some(before), None, Some(center)) => center - before,
+            (None, Some(after), Some(center)) => after - center,
+            _ => 0f64,
+        };
+        let dx = slope(heights[1][0], heights[1][2]) * self.height_scale;
+        let dy = -slope(heights[0][1], heights[2][1]) * self.height_scale;
+        normalize([-dx, -dy, 1f64])
+    }
+
+    /// Lights `color` on a surface facing `normal`, viewed from straight above.
+    pub fn shade(&self, color: Color, normal: [f64; 3]) -> Color {
+        let (sin_elevation, cos_elevation) = self.elevation.sin_cos();
+        let light = [cos_elevation * self.azimuth.cos(), cos_elevation * self.azimuth.sin(), sin_elevation];
+        let halfway = normalize([light[0], light[1], light[2] + 1f64]);
+
+        let lambert = dot(normal, light).max(0f64) as f32;
+        let highlight = if lambert > 0f32 {
+            (dot(normal, halfway).max(0f64).powf(self.shininess) as f32) * self.specular
+        } else {
+            0f32
+        };
+        color * (self.ambient + self.diffuse * lambert) + Color::new(highlight, highlight, highlight)
+    }
+}
+
+fn dot(a: [f64; 3], b: [f64; 3]) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn normalize(vector: [f64; 3]) -> [f64; 3] {
+    let length = dot(vector, vector).sqrt();
+    [vector[0] / length, vector[1] / length, vector[2] / length]
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::{FRAC_PI_3, FRAC_PI_4, FRAC_PI_6, PI};
+
+    use super::*;
+
+    fn lambert(azimuth: f64, elevation: f64) -> Lighting {
+        Lighting {
+            height: Channel::DISTANCE,
+            height_transfers: vec![Transfer::SCALE(2.0)],
+            height_scale: 1.0,
+            azimuth,
+            elevation,
+            ambient: 0.0,
+            diffuse: 1.0,
+            specular: 0.0,
+            shininess: 20.0,
+        }
+    }
+
+    fn assert_close(actual: [f64; 3], expected: [f64; 3]) {
+        for (actual_component, expected_component) in actual.iter().zip(expected) {
+            assert!((actual_component - expected_component).abs() < 1e-12, "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn flat_field_is_lit_by_light_angle() {
+        let lighting = lambert(0.0, FRAC_PI_3);
+        let normal = lighting.normal([[Some(1.0); 3]; 3]);
+        assert_close(normal, [0.0, 0.0, 1.0]);
+        // The light is `π / 6` away from the normal.
+        let shaded = lighting.shade(Color::WHITE, normal);
+        assert!((shaded.red() as f64 - FRAC_PI_6.cos()).abs() < 1e-6, "{:?}", shaded);
+    }
+
+    #[test]
+    fn slope_facing_light_is_fully_lit() {
+        // Rising to the right, so the normal leans left, where the light comes from.
+        let lighting = lambert(PI, FRAC_PI_4);
+        let row = [Some(0.0), Some(1.0), Some(2.0)];
+        let normal = lighting.normal([row; 3]);
+        let half = 0.5f64.sqrt();
+        assert_close(normal, [-half, 0.0, half]);
+        assert!((lighting.shade(Color::WHITE, normal).red() - 1.0).abs() < 1e-6);
+        // Lit from the right, the slope faces away.
+        assert_eq!(lambert(0.0, FRAC_PI_4).shade(Color::WHITE, normal), Color::BLACK);
+    }
+
+    #[test]
+    fn missing_heights_use_one_sided_slopes() {
+        let lighting = lambert(0.0, FRAC_PI_4);
+        let heights = [[None, Some(1.0), None], [Some(0.0), Some(1.0), None], [None, Some(1.0), None]];
+        let half = 0.5f64.sqrt();
+        assert_close(lighting.normal(heights), [-half, 0.0, half]);
+    }
+
+    #[test]
+    fn height_transfers() {
+        let lighting = lambert(0.0, FRAC_PI_4);
+        assert_eq!(lighting.apply_height_transfers(Some(1.5)), Some(3.0));
+        assert_eq!(lighting.apply_height_transfers(None), None);
+    }
+
+    #[test]
+    fn ambient_and_specular() {
+        let mut lighting = lambert(0.0, PI / 2.0);
+        lighting.ambient = 0.25;
+        lighting.diffuse = 0.5;
+        lighting.specular = 0.125;
+        // Lit and viewed from straight above, the highlight is at full strength.
+        let shaded = lighting.shade(Color::new(1.0, 0.0, 0.0), [0.0, 0.0, 1.0]);
+        assert!((shaded.red() - 0.875).abs() < 1e-6 && (shaded.green() - 0.125).abs() < 1e-6, "{:?}", shaded);
+    }
+}
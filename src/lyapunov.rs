@@ -0,0 +1,273 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use image::Rgb32FImage;
+
+use crate::color::Color;
+use crate::color_palette::ColorPalette;
+use crate::complex::Complex;
+use crate::fractal::{resolve_samples, Configuration, Fractal, FractalRepresentation};
+use crate::rendering_settings::RenderingSettings;
+
+/// Order in which the logistic map is driven by the parameters `a` and `b`, e.g. `AABAB`.
+#[derive(Clone, PartialEq, Debug)]
+pub struct LyapunovSequence {
+    /// `true` where the sequence uses `b`.
+    steps: Vec<bool>,
+}
+
+impl LyapunovSequence {
+    fn rate(&self, step: usize, a: f64, b: f64) -> f64 {
+        if self.steps[step % self.steps.len()] {
+            b
+        } else {
+            a
+        }
+    }
+}
+
+impl FromStr for LyapunovSequence {
+    type Err = LyapunovSequenceError;
+
+    fn from_str(sequence: &str) -> Result<Self, Self::Err> {
+        let steps = sequence.chars()
+            .enumerate()
+            .map(|(position, character)| match character {
+                'A' | 'a' => Ok(false),
+                'B' | 'b' => Ok(true),
+                _ => Err(LyapunovSequenceError::InvalidCharacter { position, character }),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if steps.is_empty() {
+            Err(LyapunovSequenceError::Empty)
+        } else {
+            Ok(LyapunovSequence { steps })
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LyapunovSequenceError {
+    Empty,
+    InvalidCharacter { position: usize, character: char },
+}
+
+impl Display for LyapunovSequenceError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LyapunovSequenceError::Empty => write!(f, "sequence must not be empty"),
+            LyapunovSequenceError::InvalidCharacter { position, character } => {
+                write!(f, "invalid character `{}` at position {}, expected `A` or `B`", character, position + 1)
+            }
+        }
+    }
+}
+
+impl Error for LyapunovSequenceError {}
+
+#[derive(Clone)]
+pub struct LyapunovConfiguration {
+    pub sequence: LyapunovSequence,
+    /// Growth rate `a` at the center of the view, plotted horizontally.
+    pub center_a: f64,
+    /// Growth rate `b` at the center of the view, plotted vertically.
+    pub center_b: f64,
+    pub zoom: f64,
+    /// Starting value of the logistic map.
+    pub initial_value: f64,
+    /// Iterations discarded before measuring, so transients do not affect the exponent.
+    pub warmup_iterations: u32,
+    /// Iterations averaged into the exponent.
+    pub iterations: u32,
+}
+
+impl Configuration for LyapunovConfiguration {}
+
+pub struct LyapunovView {
+    pub configuration: LyapunovConfiguration,
+}
+
+impl Fractal<LyapunovConfiguration> for LyapunovView {
+    type Representation = LyapunovRepresentation;
+
+    fn configuration(&self) -> LyapunovConfiguration {
+        self.configuration.clone()
+    }
+
+    fn calculate(&self, settings: &RenderingSettings) -> Self::Representation {
+        let configuration = &self.configuration;
+        let center = Complex::new(configuration.center_a, configuration.center_b);
+        let mut exponents = Vec::with_capacity(settings.sample_count());
+
+        for y in 0..settings.sample_height() {
+            for x in 0..settings.sample_width() {
+                let rates = settings.sample_location(center, configuration.zoom, x, y);
+                exponents.push(lyapunov_exponent(configuration, rates.re, rates.im));
+            }
+        }
+
+        LyapunovRepresentation {
+            configuration: configuration.clone(),
+            rendering_settings: *settings,
+            exponents,
+        }
+    }
+}
+
+fn lyapunov_exponent(configuration: &LyapunovConfiguration, a: f64, b: f64) -> f64 {
+    let sequence = &configuration.sequence;
+    let mut x = configuration.initial_value;
+    let mut step = 0;
+
+    for _ in 0..configuration.warmup_iterations {
+        x = sequence.rate(step, a, b) * x * (1f64 - x);
+        step += 1;
+    }
+
+    let mut sum = 0f64;
+    for _ in 0..configuration.iterations {
+        let rate = sequence.rate(step, a, b);
+        let derivative = (rate * (1f64 - 2f64 * x)).abs();
+        // A zero derivative is superstable, clamping keeps the sum finite.
+        sum += derivative.max(f64::MIN_POSITIVE).ln();
+        x = rate * x * (1f64 - x);
+        step += 1;
+    }
+
+    let exponent = sum / configuration.iterations.max(1) as f64;
+    // Orbits leaving the unit interval diverge, which is as chaotic as it gets.
+    if exponent.is_nan() {
+        f64::INFINITY
+    } else {
+        exponent
+    }
+}
+
+pub struct LyapunovRepresentation {
+    configuration: LyapunovConfiguration,
+    rendering_settings: RenderingSettings,
+    /// Lyapunov exponent per sample, row-major over the sample grid.
+    ///
+    /// Negative values are stable, positive ones chaotic.
+    pub exponents: Vec<f64>,
+}
+
+impl LyapunovRepresentation {
+    /// Colors stable samples from `stable` by the magnitude of their negative exponent
+    /// and chaotic samples from `chaotic` by their positive exponent.
+    pub fn colorize_regions(&self, stable: &impl ColorPalette, chaotic: &impl ColorPalette) -> Rgb32FImage {
+        resolve_samples(&self.rendering_settings, |index| {
+            let exponent = self.exponents[index];
+            if exponent > 0f64 {
+                chaotic.color_at(exponent)
+            } else {
+                stable.color_at(-exponent)
+            }
+        })
+    }
+}
+
+impl FractalRepresentation<LyapunovConfiguration> for LyapunovRepresentation {
+    fn configuration(&self) -> LyapunovConfiguration {
+        self.configuration.clone()
+    }
+
+    fn rendering_settings(&self) -> RenderingSettings {
+        self.rendering_settings
+    }
+
+    /// Colors stable samples from `palette` and leaves chaotic ones black.
+    fn colorize(&self, palette: &impl ColorPalette) -> Rgb32FImage {
+        resolve_samples(&self.rendering_settings, |index| {
+            let exponent = self.exponents[index];
+            if exponent > 0f64 {
+                Color::BLACK
+            } else {
+                palette.color_at(-exponent)
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color_palette::{KeyColors, PaletteDefinition, ScalingColorPalette};
+    use crate::color_space::ColorSpace;
+    use crate::interpolatable::Interpolation;
+    use crate::rendering_settings::{MultiSampling, Resolution};
+
+    fn configuration(sequence: &str, center_a: f64, center_b: f64) -> LyapunovConfiguration {
+        LyapunovConfiguration {
+            sequence: sequence.parse().unwrap(),
+            center_a,
+            center_b,
+            zoom: 1e6,
+            initial_value: 0.5,
+            warmup_iterations: 200,
+            iterations: 1000,
+        }
+    }
+
+    fn render(configuration: LyapunovConfiguration) -> LyapunovRepresentation {
+        let settings = RenderingSettings {
+            resolution: Resolution { width: 2, height: 2 },
+            sampling: MultiSampling::NONE,
+        };
+        LyapunovView { configuration }.calculate(&settings)
+    }
+
+    fn plain(color: Color) -> ScalingColorPalette {
+        PaletteDefinition {
+            name: None,
+            interpolation: Interpolation::LINEAR,
+            space: ColorSpace::RGB,
+            key_colors: KeyColors::from([(0.0, color), (1.0, color)]),
+        }.scaling()
+    }
+
+    #[test]
+    fn stable_exponent() {
+        // The fixed point `0.6` of `2.5 * x * (1 - x)` has the derivative `-0.5`.
+        let exponent = lyapunov_exponent(&configuration("A", 2.5, 2.5), 2.5, 2.5);
+        assert!((exponent - 0.5f64.ln()).abs() < 1e-9, "{}", exponent);
+        // `AB` with `3.2` alternates on the attracting cycle of period 2.
+        assert!(lyapunov_exponent(&configuration("AB", 3.2, 3.2), 3.2, 3.2) < 0.0);
+    }
+
+    #[test]
+    fn chaotic_exponent() {
+        // The fully chaotic logistic map at `4.0` has the exponent `ln 2`. It starts at `0.3`,
+        // as `0.5` lands on the fixed point `0`.
+        let mut configuration = configuration("AB", 4.0, 4.0);
+        configuration.initial_value = 0.3;
+        let exponent = lyapunov_exponent(&configuration, 4.0, 4.0);
+        assert!((exponent - 2f64.ln()).abs() < 0.05, "{}", exponent);
+    }
+
+    #[test]
+    fn renders_regions() {
+        let stable = render(configuration("AB", 3.2, 3.2));
+        assert!(stable.exponents.iter().all(|exponent| *exponent < 0.0));
+        let mut chaotic_configuration = configuration("AB", 3.9, 3.9);
+        chaotic_configuration.initial_value = 0.3;
+        let chaotic = render(chaotic_configuration);
+        assert!(chaotic.exponents.iter().all(|exponent| *exponent > 0.0));
+
+        let (red, blue) = (plain(Color::RED), plain(Color::BLUE));
+        assert_eq!(stable.colorize_regions(&red, &blue).get_pixel(0, 0).0, [1.0, 0.0, 0.0]);
+        assert_eq!(chaotic.colorize_regions(&red, &blue).get_pixel(1, 1).0, [0.0, 0.0, 1.0]);
+        assert_eq!(chaotic.colorize(&red).get_pixel(0, 1).0, [0.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn rejects_invalid_sequences() {
+        assert_eq!("".parse::<LyapunovSequence>(), Err(LyapunovSequenceError::Empty));
+        assert_eq!(
+            "ABC".parse::<LyapunovSequence>(),
+            Err(LyapunovSequenceError::InvalidCharacter { position: 2, character: 'C' }),
+        );
+    }
+}
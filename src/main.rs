@@ -8,8 +8,31 @@ mod complex;
 mod fractal;
 mod rendering_settings;
 mod color;
+mod encoding;
+mod gamut;
+mod alpha_color;
 mod color_palette;
+mod palette_file;
+mod palette_library;
+mod color_space;
+mod color_parsing;
+mod output;
 mod mandelbrot;
+mod escape_time;
+mod expression;
+mod buddhabrot;
+mod lyapunov;
+mod sampling;
+mod flame;
+mod mandelbox;
+mod kleinian;
+mod hybrid;
+mod orbit_trap;
+mod average_coloring;
+mod histogram_equalization;
+mod colorizer;
+mod lighting;
+mod palette_cycling;
 
 fn main() {
     let resolution = Resolution {
@@ -65,5 +88,5 @@ fn main() {
     }
 
     fs::create_dir_all("out/").unwrap();
-    imgbuf.save("out/Fractal.png").unwrap();
+    output::save_image(&imgbuf, "out/Fractal.png").unwrap();
 }
@@ -0,0 +1,144 @@
+use std::cmp::Ordering;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+use crate::complex::{Complex, Real};
+use crate::escape_time::{EscapeTimeConfiguration, EscapeTimeFormula};
+
+pub type MandelboxConfiguration<T> = EscapeTimeConfiguration<T, MandelboxFormula<T>>;
+
+/// Two dimensional Mandelbox: `z = scale * sphere_fold(box_fold(z)) + c`.
+#[derive(Clone)]
+pub struct MandelboxFormula<T> {
+    /// Usually `2.0` or `-1.5`.
+    pub scale: T,
+    /// Components beyond `±fold_limit` are reflected back, usually `1.0`.
+    pub fold_limit: T,
+    /// Points closer to the origin are scaled up linearly, usually `0.5`.
+    pub min_radius: T,
+    /// Points between `min_radius` and this are inverted in the circle, usually `1.0`.
+    pub fixed_radius: T,
+    pub bailout: T,
+}
+
+impl<T: Real> MandelboxFormula<T> {
+    /// Classic Mandelbox of `scale`, folding at `1.0` with radii `0.5` and `1.0` and bailout `100.0`.
+    pub fn new(scale: T) -> Self {
+        MandelboxFormula {
+            scale,
+            fold_limit: T::one(),
+            min_radius: T::from_f64(0.5),
+            fixed_radius: T::one(),
+            bailout: T::from_f64(100f64),
+        }
+    }
+
+    /// Mandelbox with custom folds and bailout `100.0`.
+    pub fn with_folds(scale: T, fold_limit: T, min_radius: T, fixed_radius: T) -> Result<Self, MandelboxFormulaError> {
+        if !matches!(fold_limit.partial_cmp(&T::zero()), Some(Ordering::Greater | Ordering::Equal)) {
+            return Err(MandelboxFormulaError::NegativeFoldLimit);
+        }
+        if !(min_radius > T::zero() && min_radius <= fixed_radius) {
+            return Err(MandelboxFormulaError::InvalidRadii);
+        }
+        Ok(MandelboxFormula { fold_limit, min_radius, fixed_radius, ..MandelboxFormula::new(scale) })
+    }
+
+    fn box_fold(&self, value: T) -> T {
+        if value > self.fold_limit {
+            self.fold_limit + self.fold_limit - value
+        } else if value < -self.fold_limit {
+            -self.fold_limit - self.fold_limit - value
+        } else {
+            value
+        }
+    }
+
+    fn sphere_fold(&self, z: Complex<T>) -> Complex<T> {
+        let radius_sqr = z.norm_sqr();
+        let min_radius_sqr = self.min_radius * self.min_radius;
+        let fixed_radius_sqr = self.fixed_radius * self.fixed_radius;
+        if radius_sqr < min_radius_sqr {
+            z.scale(fixed_radius_sqr / min_radius_sqr)
+        } else if radius_sqr < fixed_radius_sqr {
+            z.scale(fixed_radius_sqr / radius_sqr)
+        } else {
+            z
+        }
+    }
+}
+
+impl<T: Real> EscapeTimeFormula<T> for MandelboxFormula<T> {
+    fn step(&self, z: Complex<T>, c: Complex<T>, _iteration: u32) -> Complex<T> {
+        let folded = Complex::new(self.box_fold(z.re), self.box_fold(z.im));
+        self.sphere_fold(folded).scale(self.scale) + c
+    }
+
+    fn escaped(&self, z: Complex<T>) -> bool {
+        z.norm_sqr() > self.bailout * self.bailout
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MandelboxFormulaError {
+    NegativeFoldLimit,
+    /// The sphere fold requires `0 < min_radius <= fixed_radius`.
+    InvalidRadii,
+}
+
+impl Display for MandelboxFormulaError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MandelboxFormulaError::NegativeFoldLimit => write!(f, "fold limit must not be negative"),
+            MandelboxFormulaError::InvalidRadii => {
+                write!(f, "minimum radius must be positive and at most the fixed radius")
+            }
+        }
+    }
+}
+
+impl Error for MandelboxFormulaError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn escapes(formula: &MandelboxFormula<f64>, c: Complex<f64>) -> bool {
+        let mut z = formula.start(c);
+        for iteration in 0..1000 {
+            if formula.escaped(z) {
+                return true;
+            }
+            z = formula.step(z, c, iteration);
+        }
+        false
+    }
+
+    #[test]
+    fn origin_does_not_escape() {
+        // `0` is folded onto itself and stays there.
+        assert!(!escapes(&MandelboxFormula::new(2f64), Complex::new(0f64, 0f64)));
+    }
+
+    #[test]
+    fn far_point_escapes() {
+        assert!(escapes(&MandelboxFormula::new(2f64), Complex::new(10f64, 0f64)));
+    }
+
+    #[test]
+    fn rejects_invalid_folds() {
+        assert!(MandelboxFormula::with_folds(2f64, 1f64, 0.25f64, 1f64).is_ok());
+        assert_eq!(
+            MandelboxFormula::with_folds(2f64, -1f64, 0.5f64, 1f64).err(),
+            Some(MandelboxFormulaError::NegativeFoldLimit),
+        );
+        assert_eq!(
+            MandelboxFormula::with_folds(2f64, 1f64, 0f64, 1f64).err(),
+            Some(MandelboxFormulaError::InvalidRadii),
+        );
+        assert_eq!(
+            MandelboxFormula::with_folds(2f64, 1f64, 1.5f64, 1f64).err(),
+            Some(MandelboxFormulaError::InvalidRadii),
+        );
+    }
+}
@@ -1,40 +1,294 @@
-use image::Rgb32FImage;
+use crate::complex::{Complex, Real};
+use crate::escape_time::{EscapeTimeConfiguration, EscapeTimeFormula, EscapeTimeRepresentation};
+use crate::escape_time::{EscapeTimeView, Smoothing};
+use crate::fractal::FractalRepresentation;
 
-use crate::color_palette::ColorPalette;
-use crate::fractal::{Configuration, Fractal, FractalRepresentation};
-use crate::rendering_settings::RenderingSettings;
+pub type MandelbrotConfiguration<T> = EscapeTimeConfiguration<T, MandelbrotFormula<T>>;
+pub type MandelbrotView<T> = EscapeTimeView<T, MandelbrotFormula<T>>;
+pub type MandelbrotRepresentation<T> = EscapeTimeRepresentation<T, MandelbrotFormula<T>>;
 
-struct MandelbrotConfiguration {}
+/// `z = z^2 + c`
+#[derive(Clone)]
+pub struct MandelbrotFormula<T> {
+    /// Renders the Julia set of this `c` instead, starting the orbit at the pixel location.
+    pub julia: Option<Complex<T>>,
+    /// Usually `2.0`, larger values give smoother continuous iteration counts.
+    pub bailout: T,
+}
 
-impl Configuration for MandelbrotConfiguration {}
+impl<T: Real> EscapeTimeFormula<T> for MandelbrotFormula<T> {
+    fn start(&self, c: Complex<T>) -> Complex<T> {
+        match self.julia {
+            Some(_) => c,
+            None => Complex::zero(),
+        }
+    }
 
-struct MandelbrotView {
-    configuration: MandelbrotConfiguration,
-}
+    fn step(&self, z: Complex<T>, c: Complex<T>, _iteration: u32) -> Complex<T> {
+        z * z + self.julia.unwrap_or(c)
+    }
+
+    fn escaped(&self, z: Complex<T>) -> bool {
+        z.norm_sqr() > self.bailout * self.bailout
+    }
+
+    fn start_derivative(&self) -> Option<Complex<T>> {
+        match self.julia {
+            Some(_) => Some(Complex::one()),
+            None => Some(Complex::zero()),
+        }
+    }
+
+    fn step_derivative(&self, z: Complex<T>, derivative: Complex<T>, _iteration: u32) -> Complex<T> {
+        let doubled = z * derivative;
+        match self.julia {
+            Some(_) => doubled + doubled,
+            None => doubled + doubled + Complex::one(),
+        }
+    }
+
+    fn z_derivative(&self, z: Complex<T>, _iteration: u32) -> Option<Complex<T>> {
+        Some(z + z)
+    }
 
-impl Fractal<MandelbrotConfiguration> for MandelbrotView {
-    fn configuration(&self) -> MandelbrotConfiguration {
-        *self.configuration
+    fn second_derivatives(&self, _z: Complex<T>, _iteration: u32) -> Option<(Complex<T>, Complex<T>)> {
+        match self.julia {
+            Some(_) => None,
+            None => Some((Complex::new(T::from_f64(2f64), T::zero()), Complex::zero())),
+        }
     }
 
-    fn calculate(&self, settings: &RenderingSettings) -> MandelbrotRepresentation {
-        todo!()
+    fn smoothing(&self) -> Option<Smoothing> {
+        Some(Smoothing { bailout: self.bailout.to_f64(), degree: 2f64 })
     }
 }
 
-struct MandelbrotRepresentation {}
+/// Newton steps spent on locating nuclei for [Minibrot::find].
+const NEWTON_STEPS: u32 = 64;
+/// Iterations per period a zoomed-in configuration is given at least.
+const ITERATIONS_PER_PERIOD: u32 = 100;
 
-impl FractalRepresentation<MandelbrotConfiguration> for MandelbrotRepresentation {
-    fn configuration(&self) -> MandelbrotConfiguration {
-        todo!()
+/// Small copy of the Mandelbrot set, located by its nucleus.
+#[derive(Clone, Copy)]
+pub struct Minibrot<T> {
+    pub nucleus: Complex<T>,
+    pub period: u32,
+    /// Scale and rotation of the copy relative to the whole set.
+    pub size: Complex<T>,
+}
+
+impl<T: Real> Minibrot<T> {
+    /// Finds the minibrot with `period` whose nucleus is closest to `guess`.
+    pub fn find(guess: Complex<T>, period: u32) -> Option<Minibrot<T>> {
+        let nucleus = find_nucleus(guess, period, NEWTON_STEPS)?;
+
+        let mut z = Complex::zero();
+        let mut derivative = Complex::one();
+        let mut sum = Complex::one();
+        for _ in 1..period {
+            z = z * z + nucleus;
+            let doubled = z * derivative;
+            derivative = doubled + doubled;
+            sum += Complex::one() / derivative;
+        }
+        let size = Complex::one() / (sum * derivative * derivative);
+
+        Some(Minibrot { nucleus, period, size })
     }
 
-    fn rendering_settings(&self) -> RenderingSettings {
-        todo!()
+    /// `configuration` moved and zoomed so the minibrot is framed like the whole set at zoom `1`.
+    ///
+    /// The iteration limit is raised to at least [ITERATIONS_PER_PERIOD] times the period.
+    pub fn frame(&self, configuration: &MandelbrotConfiguration<T>) -> MandelbrotConfiguration<T> {
+        let mut configuration = configuration.clone();
+        // The whole set is framed around `-0.5`, left of its nucleus at `0`.
+        configuration.center = self.nucleus - self.size.scale(T::from_f64(0.5));
+        configuration.zoom = T::one() / T::from_f64(self.size.norm());
+        configuration.max_iterations = configuration.max_iterations.max(self.period * ITERATIONS_PER_PERIOD);
+        configuration
     }
+}
+
+/// Zooms onto the minibrot closest to the center of the view of `representation`.
+///
+/// Without `period` the period of the attracting cycle at the center is used, or its atom domain
+/// outside of the set, as the orbit of an interior sample keeps getting closer to `0` after
+/// the first period. Returns `None` for Julia sets, or if no minibrot was found.
+pub fn zoom_to_minibrot<T: Real>(
+    representation: &MandelbrotRepresentation<T>,
+    period: Option<u32>,
+) -> Option<MandelbrotConfiguration<T>> {
+    let configuration = representation.configuration();
+    if configuration.formula.julia.is_some() {
+        return None;
+    }
+
+    let period = match period {
+        Some(period) => period,
+        None => {
+            let sample = representation.sample_at(configuration.center.to_f64())?;
+            sample.period.unwrap_or(sample.atom_domain)
+        }
+    };
+    if period == 0 {
+        return None;
+    }
+
+    Minibrot::find(configuration.center, period).map(|minibrot| minibrot.frame(&configuration))
+}
 
-    fn colorize(&self, palette: &impl ColorPalette) -> Rgb32FImage {
-        todo!()
+/// Locates the nucleus of the Mandelbrot component with `period` closest to `guess`,
+/// i.e. the `c` whose critical orbit returns to `0` after `period` steps, by Newton's method
+/// in the precision of `T`.
+///
+/// Returns `None` if the iteration does not converge within `max_steps`.
+pub fn find_nucleus<T: Real>(guess: Complex<T>, period: u32, max_steps: u32) -> Option<Complex<T>> {
+    newton(guess, max_steps, |c| {
+        let mut z = Complex::zero();
+        let mut derivative = Complex::zero();
+        for _ in 0..period {
+            let doubled = z * derivative;
+            derivative = doubled + doubled + Complex::one();
+            z = z * z + c;
+        }
+        z / derivative
+    })
+}
+
+/// Locates the Misiurewicz point closest to `guess` whose orbit of `c` becomes periodic
+/// with `period` after `preperiod` steps, by Newton's method in the precision of `T`.
+/// E.g. the orbit `i, i - 1, -i, i - 1, ...` of `i` has preperiod `1` and period `2`.
+///
+/// Roots with a smaller preperiod are divided out, so the iteration does not converge to them.
+/// Returns `None` if the iteration does not converge within `max_steps`.
+pub fn find_misiurewicz<T: Real>(guess: Complex<T>, preperiod: u32, period: u32, max_steps: u32) -> Option<Complex<T>> {
+    // The critical orbit starts one step earlier, at `0`.
+    let preperiod = preperiod + 1;
+    newton(guess, max_steps, |c| {
+        let mut orbit = Vec::with_capacity((preperiod + period + 1) as usize);
+        let mut z = Complex::zero();
+        let mut derivative = Complex::zero();
+        orbit.push((z, derivative));
+        for _ in 0..preperiod + period {
+            let doubled = z * derivative;
+            derivative = doubled + doubled + Complex::one();
+            z = z * z + c;
+            orbit.push((z, derivative));
+        }
+
+        // Newton step of g(c) / prod (z[i + period] - z[i]) for i < preperiod,
+        // where g(c) = z[preperiod + period] - z[preperiod].
+        let difference = |i: usize| {
+            let (z_later, derivative_later) = orbit[i + period as usize];
+            let (z_earlier, derivative_earlier) = orbit[i];
+            (z_later - z_earlier, derivative_later - derivative_earlier)
+        };
+        let (value, value_derivative) = difference(preperiod as usize);
+        if value.norm_sqr() == T::zero() {
+            return Complex::zero();
+        }
+        let mut logarithmic_derivative = value_derivative / value;
+        for i in 0..preperiod as usize {
+            let (divisor, divisor_derivative) = difference(i);
+            logarithmic_derivative -= divisor_derivative / divisor;
+        }
+        Complex::one() / logarithmic_derivative
+    })
+}
+
+/// Applies Newton steps `c - step(c)` until they stop changing `c` in the precision of `T`.
+fn newton<T: Real>(guess: Complex<T>, max_steps: u32, step: impl Fn(Complex<T>) -> Complex<T>) -> Option<Complex<T>> {
+    let tolerance = T::epsilon().to_f64() * 4f64;
+    let mut c = guess;
+    for _ in 0..max_steps {
+        let delta = step(c);
+        let delta_norm = delta.norm();
+        if !delta_norm.is_finite() {
+            return None;
+        }
+        c -= delta;
+        if delta_norm <= tolerance * c.norm() {
+            return Some(c);
+        }
     }
+    None
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fractal::Fractal;
+    use crate::rendering_settings::{MultiSampling, RenderingSettings, Resolution};
+
+    fn assert_close(actual: Complex<f64>, expected: Complex<f64>) {
+        let tolerance = f64::EPSILON * 4f64 * expected.norm();
+        assert!((actual - expected).norm() <= tolerance, "{}{:+}i", actual.re, actual.im);
+    }
+
+    #[test]
+    fn finds_period_three_nucleus() {
+        let nucleus = find_nucleus(Complex::new(-0.12f64, 0.75f64), 3, NEWTON_STEPS).unwrap();
+        assert_close(nucleus, Complex::new(-0.12256116687665362f64, 0.7448617666197442f64));
+    }
+
+    fn configuration(center: Complex<f64>, zoom: f64) -> MandelbrotConfiguration<f64> {
+        MandelbrotConfiguration {
+            formula: MandelbrotFormula { julia: None, bailout: 2f64 },
+            center,
+            zoom,
+            max_iterations: 1000,
+            orbit_trap: None,
+            average_colorings: Vec::new(),
+            interior_distance: false,
+        }
+    }
+
+    #[test]
+    fn finds_period_three_minibrot() {
+        let minibrot = Minibrot::find(Complex::new(-1.75f64, 0f64), 3).unwrap();
+        assert_close(minibrot.nucleus, Complex::new(-1.7548776662466927f64, 0f64));
+        assert!((minibrot.size.norm() - 0.019).abs() < 0.001, "{}", minibrot.size.norm());
+    }
+
+    #[test]
+    fn frames_minibrot() {
+        let minibrot = Minibrot::find(Complex::new(-1.75f64, 0f64), 3).unwrap();
+        let framed = minibrot.frame(&configuration(Complex::new(-0.5f64, 0f64), 1f64));
+        // Framed like the whole set, whose nucleus `0` is half a unit right of the center `-0.5`.
+        assert_close(framed.center + minibrot.size.scale(0.5f64), minibrot.nucleus);
+        assert!((framed.zoom * minibrot.size.norm() - 1f64).abs() < 1e-12);
+        assert_eq!(framed.max_iterations, 1000);
+    }
+
+    #[test]
+    fn zooms_to_minibrot_of_atom_domain() {
+        let view = MandelbrotView { configuration: configuration(Complex::new(-1.7549f64, 0f64), 20f64) };
+        let settings = RenderingSettings {
+            resolution: Resolution { width: 9, height: 9 },
+            sampling: MultiSampling::NONE,
+        };
+        let representation = view.calculate(&settings);
+
+        let detected = zoom_to_minibrot(&representation, None).unwrap();
+        let explicit = zoom_to_minibrot(&representation, Some(3)).unwrap();
+        let minibrot = Minibrot::find(Complex::new(-1.7549f64, 0f64), 3).unwrap();
+        for framed in [detected, explicit] {
+            assert_close(framed.center, minibrot.frame(&view.configuration).center);
+            assert_eq!(framed.zoom, 1f64 / minibrot.size.norm());
+        }
+    }
+
+    #[test]
+    fn finds_misiurewicz_point_at_i() {
+        // The critical orbit of `i` is `0, i, i - 1, -i, i - 1, ...`.
+        let point = find_misiurewicz(Complex::new(0.05f64, 0.95f64), 1, 2, NEWTON_STEPS).unwrap();
+        assert_close(point, Complex::new(0f64, 1f64));
+    }
+
+    #[test]
+    fn finds_misiurewicz_point_at_tip() {
+        // The orbit of `-2` is `-2, 2, 2, ...`.
+        let point = find_misiurewicz(Complex::new(-1.9f64, 0.05f64), 1, 1, NEWTON_STEPS).unwrap();
+        assert_close(point, Complex::new(-2f64, 0f64));
+    }
+}
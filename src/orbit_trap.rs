@@ -0,0 +1,135 @@
+use std::sync::Arc;
+
+use image::Rgb32FImage;
+
+use crate::color::Color;
+use crate::complex::ComplexF64;
+
+/// Shape whose distance to the orbit is tracked during iteration.
+#[derive(Clone)]
+pub enum OrbitTrap {
+    POINT(ComplexF64),
+    /// Line through `point`, rotated by `angle` radians against the real axis.
+    LINE { point: ComplexF64, angle: f64 },
+    /// Horizontal and vertical line through a point.
+    CROSS(ComplexF64),
+    CIRCLE { center: ComplexF64, radius: f64 },
+    /// Texture covering the rectangle spanning `size` from its lower left `corner`.
+    ///
+    /// Points on the texture have a distance of zero, so the first orbit point landing on it is kept.
+    IMAGE { texture: Arc<Rgb32FImage>, corner: ComplexF64, size: ComplexF64 },
+}
+
+/// Closest approach of an orbit to an [OrbitTrap].
+#[derive(Clone, Copy)]
+pub struct TrapHit {
+    pub distance: f64,
+    /// Iteration of the closest orbit point.
+    pub iteration: u32,
+    /// Location of the closest orbit point.
+    pub position: ComplexF64,
+}
+
+impl OrbitTrap {
+    pub fn distance(&self, z: ComplexF64) -> f64 {
+        match self {
+            OrbitTrap::POINT(point) => (z - *point).norm(),
+            OrbitTrap::LINE { point, angle } => {
+                let (sin, cos) = angle.sin_cos();
+                ((z.im - point.im) * cos - (z.re - point.re) * sin).abs()
+            }
+            OrbitTrap::CROSS(point) => (z.re - point.re).abs().min((z.im - point.im).abs()),
+            OrbitTrap::CIRCLE { center, radius } => ((z - *center).norm() - radius).abs(),
+            OrbitTrap::IMAGE { corner, size, .. } => {
+                let dx = (corner.re - z.re).max(z.re - (corner.re + size.re)).max(0f64);
+                let dy = (corner.im - z.im).max(z.im - (corner.im + size.im)).max(0f64);
+                dx.hypot(dy)
+            }
+        }
+    }
+
+    /// Updates `hit` if `z`, reached after `iteration` steps, is strictly closer to the trap.
+    pub fn record(&self, hit: &mut TrapHit, z: ComplexF64, iteration: u32) {
+        let distance = self.distance(z);
+        if distance < hit.distance {
+            *hit = TrapHit { distance, iteration, position: z };
+        }
+    }
+
+    /// Texture color at the closest orbit point, for [OrbitTrap::IMAGE] traps which were hit.
+    pub fn texture_color(&self, hit: &TrapHit) -> Option<Color> {
+        match self {
+            OrbitTrap::IMAGE { texture, corner, size } if hit.distance == 0f64 => {
+                let u = (hit.position.re - corner.re) / size.re;
+                let v = 1f64 - (hit.position.im - corner.im) / size.im;
+                let x = ((u * texture.width() as f64) as u32).min(texture.width().saturating_sub(1));
+                let y = ((v * texture.height() as f64) as u32).min(texture.height().saturating_sub(1));
+                let pixel = texture.get_pixel(x, y);
+                Some(Color::new(pixel[0], pixel[1], pixel[2]))
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::FRAC_PI_2;
+
+    use super::*;
+    use crate::complex::Complex;
+
+    /// Closest approach to `trap` of the orbit `-1, 0, -1, 0` of `c = -1`, skipping the start `0`.
+    fn closest(trap: &OrbitTrap) -> TrapHit {
+        let mut hit = TrapHit { distance: f64::INFINITY, iteration: 0, position: Complex::zero() };
+        let mut z = Complex::zero();
+        for iteration in 1..=4 {
+            z = z * z + Complex::new(-1f64, 0f64);
+            trap.record(&mut hit, z, iteration);
+        }
+        hit
+    }
+
+    #[test]
+    fn point_trap() {
+        let hit = closest(&OrbitTrap::POINT(Complex::new(0.5, 0.0)));
+        assert_eq!(hit.distance, 0.5);
+        assert_eq!(hit.iteration, 2);
+        assert_eq!(hit.position.norm(), 0.0);
+    }
+
+    #[test]
+    fn line_trap() {
+        let vertical = closest(&OrbitTrap::LINE { point: Complex::new(-0.75, 3.0), angle: FRAC_PI_2 });
+        assert!((vertical.distance - 0.25).abs() < 1e-12);
+        assert_eq!(vertical.iteration, 1);
+
+        // Every orbit point has the same distance to the horizontal line, so the first one is kept.
+        let horizontal = closest(&OrbitTrap::LINE { point: Complex::new(5.0, 1.0), angle: 0.0 });
+        assert_eq!(horizontal.distance, 1.0);
+        assert_eq!(horizontal.iteration, 1);
+    }
+
+    #[test]
+    fn shape_distances() {
+        let z = Complex::new(3.0, 4.0);
+        assert_eq!(OrbitTrap::CROSS(Complex::new(1.0, 1.0)).distance(z), 2.0);
+        assert_eq!(OrbitTrap::CIRCLE { center: Complex::zero(), radius: 2.0 }.distance(z), 3.0);
+        assert_eq!(OrbitTrap::CIRCLE { center: Complex::zero(), radius: 7.0 }.distance(z), 2.0);
+    }
+
+    #[test]
+    fn image_trap_samples_texture() {
+        let texture = Rgb32FImage::from_fn(2, 2, |x, y| image::Rgb([x as f32, y as f32, 0.0]));
+        let size = Complex::new(2.0, 2.0);
+        let trap = OrbitTrap::IMAGE { texture: Arc::new(texture), corner: Complex::zero(), size };
+        assert_eq!(trap.distance(Complex::new(1.5, 0.5)), 0.0);
+        assert_eq!(trap.distance(Complex::new(5.0, 6.0)), 5.0);
+
+        // The lower right quarter, as images run downwards.
+        let hit = TrapHit { distance: 0.0, iteration: 1, position: Complex::new(1.5, 0.5) };
+        assert_eq!(trap.texture_color(&hit), Some(Color::new(1.0, 1.0, 0.0)));
+        let miss = TrapHit { distance: 0.5, ..hit };
+        assert_eq!(trap.texture_color(&miss), None);
+    }
+}
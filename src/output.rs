@@ -0,0 +1,82 @@
+use std::path::Path;
+
+use exr::math::Vec2;
+use exr::meta::attribute;
+use exr::prelude::{Image, Layer, LayerAttributes, SpecificChannels, WritableImage};
+use image::{DynamicImage, ImageFormat, ImageResult, Rgb32FImage, Rgba32FImage};
+
+use crate::encoding::Encoding;
+use crate::gamut::{self, Gamut};
+
+/// Saves a linear light image, choosing the encoding by the format of `path`.
+///
+/// OpenEXR stores the linear floats, PNG and TIFF sRGB encoded 16 bit and all other formats
+/// sRGB encoded 8 bit components.
+pub fn save_image(image: &Rgb32FImage, path: impl AsRef<Path>) -> ImageResult<()> {
+    let path = path.as_ref();
+    if ImageFormat::from_path(path)? == ImageFormat::OpenExr {
+        return image.save(path);
+    }
+
+    let mut encoded = image.clone();
+    for pixel in encoded.pixels_mut() {
+        pixel.0 = pixel.0.map(|component| Encoding::SRGB.encode(component));
+    }
+    save_encoded(DynamicImage::ImageRgb32F(encoded), path)
+}
+
+/// Like [save_image], keeping the straight alpha channel, which is never encoded.
+pub fn save_alpha_image(image: &Rgba32FImage, path: impl AsRef<Path>) -> ImageResult<()> {
+    let path = path.as_ref();
+    if ImageFormat::from_path(path)? == ImageFormat::OpenExr {
+        return image.save(path);
+    }
+
+    let mut encoded = image.clone();
+    for pixel in encoded.pixels_mut() {
+        let [red, green, blue, alpha] = pixel.0;
+        let [red, green, blue] = [red, green, blue].map(|component| Encoding::SRGB.encode(component));
+        pixel.0 = [red, green, blue, alpha];
+    }
+    save_encoded(DynamicImage::ImageRgba32F(encoded), path)
+}
+
+/// Saves a linear light image as OpenEXR with the primaries of `gamut`, tagged with its chromaticities.
+///
+/// Components are neither clamped nor encoded, so highlights above `1.0` and wide-gamut colors survive.
+pub fn save_hdr_image(image: &Rgb32FImage, gamut: Gamut, path: impl AsRef<Path>) -> exr::error::Result<()> {
+    let matrix = gamut.matrix_from_srgb();
+    let pixels = SpecificChannels::rgb(|position: Vec2<usize>| {
+        let [red, green, blue] = gamut::apply(&matrix, image.get_pixel(position.x() as u32, position.y() as u32).0);
+        (red, green, blue)
+    });
+    let layer = Layer::new(
+        (image.width() as usize, image.height() as usize),
+        LayerAttributes::named("rgb"),
+        exr::prelude::Encoding::FAST_LOSSLESS,
+        pixels,
+    );
+
+    let chromaticities = gamut.chromaticities();
+    let point = |[x, y]: [f64; 2]| Vec2(x as f32, y as f32);
+    let mut exr_image = Image::from_layer(layer);
+    exr_image.attributes.chromaticities = Some(attribute::Chromaticities {
+        red: point(chromaticities.red),
+        green: point(chromaticities.green),
+        blue: point(chromaticities.blue),
+        white: point(chromaticities.white),
+    });
+    exr_image.write().to_file(path)
+}
+
+/// Quantizes encoded floats to the bit depth fitting the format of `path`.
+fn save_encoded(image: DynamicImage, path: &Path) -> ImageResult<()> {
+    let sixteen_bit = matches!(ImageFormat::from_path(path)?, ImageFormat::Png | ImageFormat::Tiff);
+    let quantized = match (sixteen_bit, image.color().has_alpha()) {
+        (true, false) => DynamicImage::ImageRgb16(image.into_rgb16()),
+        (true, true) => DynamicImage::ImageRgba16(image.into_rgba16()),
+        (false, false) => DynamicImage::ImageRgb8(image.into_rgb8()),
+        (false, true) => DynamicImage::ImageRgba8(image.into_rgba8()),
+    };
+    quantized.save(path)
+}
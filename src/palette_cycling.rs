@@ -0,0 +1,183 @@
+use std::path::{Path, PathBuf};
+
+use image::ImageResult;
+
+use crate::color_palette::{ColorPalette, PaletteMapping};
+use crate::colorizer::Colorizer;
+use crate::escape_time::EscapeTimeRepresentation;
+use crate::fractal::{Configuration, FractalRepresentation};
+use crate::output::save_image;
+
+/// Palette cycling animation, which shifts the palette offset from frame to frame.
+///
+/// Frames only recolorize a stored representation, so iterations are calculated once.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct PaletteCycling {
+    pub frames: u32,
+    /// Change of the palette offset between frames, negative to cycle backwards.
+    pub step: f64,
+}
+
+impl PaletteCycling {
+    /// Cycles once through a palette repeating every `period` over all frames, so the animation loops.
+    ///
+    /// Returns `None` without frames, as there is no step to loop in.
+    pub fn looping(frames: u32, period: f64) -> Option<PaletteCycling> {
+        if frames == 0 {
+            return None;
+        }
+        Some(PaletteCycling { frames, step: period / frames as f64 })
+    }
+
+    /// `mapping` shifted to `frame`.
+    pub fn mapping(&self, mapping: PaletteMapping, frame: u32) -> PaletteMapping {
+        PaletteMapping { offset: mapping.offset + self.step * frame as f64, ..mapping }
+    }
+
+    /// Calls `frame` with every frame index and `palette` shifted to it, stopping at the first error.
+    /// The mapping of `palette` is restored afterwards.
+    pub fn frames<P: ColorPalette + ?Sized, E>(
+        &self,
+        palette: &mut P,
+        mut frame: impl FnMut(u32, &P) -> Result<(), E>,
+    ) -> Result<(), E> {
+        let mapping = palette.mapping();
+        let result = (0..self.frames).try_for_each(|index| {
+            palette.set_mapping(self.mapping(mapping, index));
+            frame(index, palette)
+        });
+        palette.set_mapping(mapping);
+        result
+    }
+
+    /// Like [PaletteCycling::frames], shifting the palettes of all layers of `colorizer` together.
+    pub fn colorizer_frames<E>(
+        &self,
+        colorizer: &mut Colorizer,
+        mut frame: impl FnMut(u32, &Colorizer) -> Result<(), E>,
+    ) -> Result<(), E> {
+        let mappings: Vec<PaletteMapping> = colorizer.all_layers_mut().map(|layer| layer.palette.mapping()).collect();
+        let result = (0..self.frames).try_for_each(|index| {
+            for (layer, mapping) in colorizer.all_layers_mut().zip(&mappings) {
+                layer.palette.set_mapping(self.mapping(*mapping, index));
+            }
+            frame(index, colorizer)
+        });
+        for (layer, mapping) in colorizer.all_layers_mut().zip(mappings) {
+            layer.palette.set_mapping(mapping);
+        }
+        result
+    }
+
+    /// Saves all frames of `representation` colorized with `palette` into `directory`,
+    /// named `frame_00000.<extension>` and so on.
+    pub fn save<C: Configuration>(
+        &self,
+        representation: &impl FractalRepresentation<C>,
+        palette: &mut impl ColorPalette,
+        directory: impl AsRef<Path>,
+        extension: &str,
+    ) -> ImageResult<()> {
+        self.frames(palette, |index, palette| {
+            save_image(&representation.colorize(palette), frame_path(directory.as_ref(), index, extension))
+        })
+    }
+
+    /// Like [PaletteCycling::save], colorizing escape time fractals in layers.
+    pub fn save_layers<T, F>(
+        &self,
+        representation: &EscapeTimeRepresentation<T, F>,
+        colorizer: &mut Colorizer,
+        directory: impl AsRef<Path>,
+        extension: &str,
+    ) -> ImageResult<()> {
+        self.colorizer_frames(colorizer, |index, colorizer| {
+            save_image(&representation.colorize_layers(colorizer), frame_path(directory.as_ref(), index, extension))
+        })
+    }
+}
+
+fn frame_path(directory: &Path, frame: u32, extension: &str) -> PathBuf {
+    directory.join(format!("frame_{:05}.{}", frame, extension))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alpha_color::AlphaColor;
+    use crate::color::Color;
+    use crate::color_palette::{KeyColors, PaletteDefinition};
+    use crate::color_space::ColorSpace;
+    use crate::colorizer::{BlendMode, Channel, ColorizerLayer};
+    use crate::interpolatable::Interpolation;
+
+    fn palette(offset: f64) -> Box<dyn ColorPalette> {
+        let mut palette = PaletteDefinition {
+            name: None,
+            interpolation: Interpolation::LINEAR,
+            space: ColorSpace::RGB,
+            key_colors: KeyColors::from([(0.0, Color::BLACK), (1.0, Color::WHITE)]),
+        }.repeating();
+        palette.set_mapping(PaletteMapping { offset, ..PaletteMapping::default() });
+        Box::new(palette)
+    }
+
+    fn layer(offset: f64) -> ColorizerLayer {
+        ColorizerLayer {
+            channel: Channel::ITERATION,
+            transfers: Vec::new(),
+            palette: palette(offset),
+            blend: BlendMode::NORMAL,
+            opacity: 1.0,
+        }
+    }
+
+    #[test]
+    fn looping_covers_period() {
+        let cycling = PaletteCycling::looping(4, 1.0).unwrap();
+        assert_eq!(cycling.step, 0.25);
+        assert_eq!(cycling.mapping(PaletteMapping::default(), 4).offset, 1.0);
+        assert_eq!(PaletteCycling::looping(0, 1.0), None);
+    }
+
+    #[test]
+    fn frames_shift_offset() {
+        let cycling = PaletteCycling { frames: 3, step: 0.5 };
+        let mut palette = palette(0.25);
+        let mut offsets = Vec::new();
+        cycling.frames(palette.as_mut(), |_, palette| -> Result<(), ()> {
+            offsets.push(palette.mapping().offset);
+            Ok(())
+        }).unwrap();
+        assert_eq!(offsets, vec![0.25, 0.75, 1.25]);
+        assert_eq!(palette.mapping().offset, 0.25);
+    }
+
+    #[test]
+    fn frames_restore_mapping_after_error() {
+        let cycling = PaletteCycling { frames: 5, step: 0.1 };
+        let mut palette = palette(0.5);
+        let result = cycling.frames(palette.as_mut(), |index, _| if index == 2 { Err(index) } else { Ok(()) });
+        assert_eq!(result, Err(2));
+        assert_eq!(palette.mapping().offset, 0.5);
+    }
+
+    #[test]
+    fn colorizer_frames_restore_mappings_after_error() {
+        let cycling = PaletteCycling { frames: 5, step: 0.1 };
+        let mut colorizer = Colorizer::exterior(AlphaColor::TRANSPARENT, vec![layer(0.5), layer(0.0)]);
+        colorizer.interior_layers.push(layer(0.25));
+
+        let mut frames = 0;
+        let result = cycling.colorizer_frames(&mut colorizer, |index, colorizer| {
+            frames += 1;
+            let offsets: Vec<f64> = colorizer.layers.iter().map(|layer| layer.palette.mapping().offset).collect();
+            assert!((offsets[0] - offsets[1] - 0.5).abs() < 1e-12);
+            if index == 3 { Err(index) } else { Ok(()) }
+        });
+        assert_eq!(result, Err(3));
+        assert_eq!(frames, 4);
+        let offsets: Vec<f64> = colorizer.all_layers_mut().map(|layer| layer.palette.mapping().offset).collect();
+        assert_eq!(offsets, vec![0.5, 0.0, 0.25]);
+    }
+}
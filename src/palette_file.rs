@@ -0,0 +1,588 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter, Write};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use ron::ser::PrettyConfig;
+
+use crate::color::{to_byte, Color};
+use crate::color_palette::{KeyColors, PaletteDefinition};
+use crate::color_space::ColorSpace;
+use crate::encoding::Encoding;
+use crate::interpolatable::Interpolation;
+
+/// Distance between the two keys of a hard edge, as key colors can not change color at a single position.
+const HARD_EDGE: f64 = 1e-9;
+/// Number of color positions of an Ultra Fractal gradient.
+const UGR_POSITIONS: f64 = 400f64;
+/// Number of colors written to Fractint maps.
+const MAP_COLORS: usize = 256;
+
+/// Segment blending functions of GIMP gradients.
+const GGR_LINEAR: u32 = 0;
+const GGR_CURVED: u32 = 1;
+const GGR_SINE: u32 = 2;
+const GGR_STEP: u32 = 5;
+/// Segment coloring types of GIMP gradients.
+const GGR_RGB: u32 = 0;
+const GGR_HSV_CCW: u32 = 1;
+const GGR_HSV_CW: u32 = 2;
+
+/// Loads all palettes of a GIMP `.ggr`, Fractint `.map` or Ultra Fractal `.ugr` file, or the
+/// [PaletteDefinition] serialized in a `.toml`, `.json` or `.ron` file.
+pub fn load_palettes(path: impl AsRef<Path>) -> Result<Vec<PaletteDefinition>, PaletteFileError> {
+    let path = path.as_ref();
+    let parse = match extension(path).as_str() {
+        "toml" => |contents: &str| Ok(vec![toml::from_str(contents).map_err(definition_error)?]),
+        "json" => |contents: &str| Ok(vec![serde_json::from_str(contents).map_err(definition_error)?]),
+        "ron" => |contents: &str| Ok(vec![ron::from_str(contents).map_err(definition_error)?]),
+        "ggr" => |contents: &str| Ok(vec![parse_ggr(contents)?]),
+        "map" => |contents: &str| Ok(vec![parse_map(contents)?]),
+        "ugr" => parse_ugr,
+        extension => return Err(PaletteFileError::UnknownFormat(extension.to_string())),
+    };
+    let mut palettes = parse(&fs::read_to_string(path)?)?;
+    // Maps have no name inside the file.
+    for palette in palettes.iter_mut().filter(|palette| palette.name.is_none()) {
+        palette.name = path.file_stem().map(|stem| stem.to_string_lossy().into_owned());
+    }
+    Ok(palettes)
+}
+
+/// Saves `palette` as `.toml`, `.json`, `.ron`, GIMP `.ggr` or Fractint `.map` file, depending on
+/// the extension of `path`.
+pub fn save_palette(palette: &PaletteDefinition, path: impl AsRef<Path>) -> Result<(), PaletteFileError> {
+    let path = path.as_ref();
+    let contents = match extension(path).as_str() {
+        "toml" => toml::to_string_pretty(palette).map_err(definition_error)?,
+        "json" => serde_json::to_string_pretty(palette).map_err(definition_error)?,
+        "ron" => ron::ser::to_string_pretty(palette, PrettyConfig::default()).map_err(definition_error)?,
+        "ggr" => to_ggr(palette),
+        "map" => to_map(palette),
+        extension => return Err(PaletteFileError::UnknownFormat(extension.to_string())),
+    };
+    Ok(fs::write(path, contents)?)
+}
+
+fn extension(path: &Path) -> String {
+    path.extension().map_or(String::new(), |extension| extension.to_string_lossy().to_ascii_lowercase())
+}
+
+/// Parses a GIMP gradient.
+///
+/// Segments become key colors at their ends and, if off center, at their midpoints. Step segments
+/// and segments not continuing the color of the previous one become hard edges. The palette
+/// interpolates cubic if all smooth segments use sine blending, and in HSV if all segments do,
+/// taking the shorter way around the hue circle regardless of the segment direction.
+/// Opacity is dropped.
+pub fn parse_ggr(contents: &str) -> Result<PaletteDefinition, PaletteFileError> {
+    let mut lines = contents.lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty());
+    if lines.next().map(|(_, line)| line) != Some("GIMP Gradient") {
+        return Err(PaletteFileError::MissingHeader);
+    }
+
+    let (mut number, mut line) = lines.next().ok_or(PaletteFileError::UnexpectedEnd)?;
+    let mut name = None;
+    if let Some(value) = line.strip_prefix("Name:") {
+        name = Some(value.trim().to_string());
+        (number, line) = lines.next().ok_or(PaletteFileError::UnexpectedEnd)?;
+    }
+    let count: usize = line.parse().map_err(|_| invalid_line(number, line))?;
+
+    let lines: Vec<(usize, &str)> = lines.collect();
+    let segments = lines.iter()
+        .map(|&(number, line)| GgrSegment::parse(line).ok_or_else(|| invalid_line(number, line)))
+        .collect::<Result<Vec<_>, _>>()?;
+    if segments.len() != count {
+        return Err(PaletteFileError::SegmentCount { expected: count, found: segments.len() });
+    }
+    if segments.is_empty() {
+        return Err(PaletteFileError::Empty);
+    }
+
+    let space = if segments.iter().all(|segment| segment.coloring != GGR_RGB) {
+        ColorSpace::HSV
+    } else {
+        ColorSpace::RGB
+    };
+    let mut smooth = segments.iter().filter(|segment| segment.blending != GGR_STEP).peekable();
+    let interpolation = if smooth.peek().is_some() && smooth.all(|segment| segment.blending == GGR_SINE) {
+        Interpolation::CUBIC
+    } else {
+        Interpolation::LINEAR
+    };
+
+    let mut keys = Vec::new();
+    for (segment, &(number, line)) in segments.iter().zip(&lines) {
+        // Segments must be sorted and must not overlap.
+        let mut push = |position, color| {
+            push_key(&mut keys, position, color).then_some(()).ok_or_else(|| invalid_line(number, line))
+        };
+        push(segment.left, segment.left_color)?;
+        if segment.blending == GGR_STEP {
+            push(segment.middle, segment.left_color)?;
+            push(segment.middle, segment.right_color)?;
+        } else if (segment.middle - (segment.left + segment.right) / 2f64).abs() > HARD_EDGE {
+            // Linear and curved blending both reach the average color at the midpoint.
+            let middle = space.interpolate(Interpolation::LINEAR, segment.left_color, 0.5, segment.right_color);
+            push(segment.middle, middle)?;
+        }
+        push(segment.right, segment.right_color)?;
+    }
+
+    Ok(PaletteDefinition { name, interpolation, space, key_colors: keys.into_iter().collect() })
+}
+
+struct GgrSegment {
+    left: f64,
+    middle: f64,
+    right: f64,
+    left_color: Color,
+    right_color: Color,
+    blending: u32,
+    coloring: u32,
+}
+
+impl GgrSegment {
+    /// Parses `left middle right r g b a r g b a blending coloring`, optionally followed by endpoint types.
+    fn parse(line: &str) -> Option<GgrSegment> {
+        let values = line.split_whitespace().map(str::parse).collect::<Result<Vec<f64>, _>>().ok()?;
+        if values.len() < 13 {
+            return None;
+        }
+        let color = |offset: usize| {
+            Color::decode(Encoding::SRGB, [values[offset] as f32, values[offset + 1] as f32, values[offset + 2] as f32])
+        };
+        Some(GgrSegment {
+            left: values[0],
+            middle: values[1],
+            right: values[2],
+            left_color: color(3),
+            right_color: color(7),
+            blending: values[11] as u32,
+            coloring: values[12] as u32,
+        })
+    }
+}
+
+/// Parses a Fractint map of sRGB encoded `red green blue` lines with values `0..=255`, where
+/// anything after the third value is a comment.
+///
+/// The colors are spread evenly over `0.0..1.0` and the first one repeats at `1.0`, as maps are cyclic.
+pub fn parse_map(contents: &str) -> Result<PaletteDefinition, PaletteFileError> {
+    let mut colors = Vec::new();
+    for (index, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(';') {
+            continue;
+        }
+        let mut values = line.split_whitespace().map(str::parse::<u8>);
+        let color = match (values.next(), values.next(), values.next()) {
+            (Some(Ok(red)), Some(Ok(green)), Some(Ok(blue))) => [red, green, blue],
+            _ => return Err(invalid_line(index + 1, line)),
+        };
+        colors.push(Color::decode(Encoding::SRGB, color.map(|value| value as f32 / 255f32)));
+    }
+    if colors.is_empty() {
+        return Err(PaletteFileError::Empty);
+    }
+
+    let count = colors.len() as f64;
+    let mut key_colors: KeyColors = colors.iter()
+        .enumerate()
+        .map(|(index, color)| (index as f64 / count, *color))
+        .collect();
+    key_colors.insert(1f64, colors[0]);
+    Ok(PaletteDefinition { name: None, interpolation: Interpolation::LINEAR, space: ColorSpace::RGB, key_colors })
+}
+
+/// Parses all gradients of an Ultra Fractal gradient file.
+///
+/// Smooth gradients interpolate cubic, others linear. The gradients wrap around, so the color
+/// between the last and first index is repeated at `0.0` and `1.0`. Opacity is dropped.
+pub fn parse_ugr(contents: &str) -> Result<Vec<PaletteDefinition>, PaletteFileError> {
+    let mut palettes = Vec::new();
+    let mut current: Option<UgrGradient> = None;
+
+    for (index, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(';') {
+            continue;
+        }
+        let gradient = match current.as_mut() {
+            Some(gradient) => gradient,
+            None => {
+                let name = line.strip_suffix('{').ok_or_else(|| invalid_line(index + 1, line))?;
+                current = Some(UgrGradient::new(name.trim()));
+                continue;
+            }
+        };
+
+        if line == "}" {
+            palettes.push(gradient.finish()?);
+            current = None;
+        } else if let Some(section) = line.strip_suffix(':') {
+            gradient.in_colors = section.trim() == "gradient";
+        } else if gradient.in_colors {
+            gradient.parse_line(line).ok_or_else(|| invalid_line(index + 1, line))?;
+        }
+    }
+
+    if current.is_some() {
+        return Err(PaletteFileError::UnexpectedEnd);
+    }
+    if palettes.is_empty() {
+        return Err(PaletteFileError::Empty);
+    }
+    Ok(palettes)
+}
+
+struct UgrGradient {
+    name: String,
+    title: Option<String>,
+    smooth: bool,
+    /// Whether the current section is the `gradient:` one, the only one containing colors.
+    in_colors: bool,
+    keys: Vec<(i32, Color)>,
+}
+
+impl UgrGradient {
+    fn new(name: &str) -> UgrGradient {
+        UgrGradient { name: name.to_string(), title: None, smooth: false, in_colors: false, keys: Vec::new() }
+    }
+
+    fn parse_line(&mut self, line: &str) -> Option<()> {
+        let (mut index, mut color) = (None, None);
+        for (key, value) in attributes(line)? {
+            match key {
+                "title" => self.title = Some(value.to_string()),
+                "smooth" => self.smooth = value == "yes",
+                "index" => index = Some(value.parse::<i32>().ok()?),
+                "color" => color = Some(value.parse::<u32>().ok()?),
+                _ => {}
+            }
+        }
+        match (index, color) {
+            (Some(index), Some(color)) => {
+                // Colors are stored as 0xBBGGRR.
+                let [red, green, blue, _] = color.to_le_bytes();
+                let color = Color::decode(Encoding::SRGB, [red, green, blue].map(|value| value as f32 / 255f32));
+                self.keys.push((index.rem_euclid(UGR_POSITIONS as i32), color));
+                Some(())
+            }
+            (None, None) => Some(()),
+            _ => None,
+        }
+    }
+
+    fn finish(&mut self) -> Result<PaletteDefinition, PaletteFileError> {
+        self.keys.sort_by_key(|(index, _)| *index);
+        let (&(first, first_color), &(last, last_color)) = match (self.keys.first(), self.keys.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return Err(PaletteFileError::Empty),
+        };
+        let interpolation = if self.smooth { Interpolation::CUBIC } else { Interpolation::LINEAR };
+
+        let mut key_colors: KeyColors = self.keys.iter()
+            .map(|(index, color)| (*index as f64 / UGR_POSITIONS, *color))
+            .collect();
+        let wrap_color = if first == 0 {
+            first_color
+        } else {
+            let ratio = (UGR_POSITIONS - last as f64) / (UGR_POSITIONS + (first - last) as f64);
+            ColorSpace::RGB.interpolate(interpolation, last_color, ratio, first_color)
+        };
+        key_colors.insert(0f64, wrap_color);
+        key_colors.insert(1f64, wrap_color);
+
+        Ok(PaletteDefinition {
+            name: Some(self.title.take().unwrap_or_else(|| self.name.clone())),
+            interpolation,
+            space: ColorSpace::RGB,
+            key_colors,
+        })
+    }
+}
+
+/// Splits `key=value key="quoted value"` pairs.
+fn attributes(line: &str) -> Option<Vec<(&str, &str)>> {
+    let mut attributes = Vec::new();
+    let mut rest = line.trim_start();
+    while !rest.is_empty() {
+        let (key, after) = rest.split_once('=')?;
+        let (value, after) = match after.strip_prefix('"') {
+            Some(quoted) => quoted.split_once('"')?,
+            None => after.split_once(char::is_whitespace).unwrap_or((after, "")),
+        };
+        attributes.push((key.trim(), value));
+        rest = after.trim_start();
+    }
+    Some(attributes)
+}
+
+/// Formats `palette` as GIMP gradient with one segment between each pair of neighbouring keys.
+///
+/// Key positions are mapped from the range spanning both the keys and `0.0..=1.0` onto `0.0..=1.0`.
+/// Cubic interpolation becomes sine, nearest step and exponential curved blending.
+pub fn to_ggr(palette: &PaletteDefinition) -> String {
+    let (start, end) = span(palette);
+    let mut keys: Vec<(f64, Color)> = palette.key_colors.iter()
+        .map(|(position, color)| ((position - start) / (end - start), color))
+        .collect();
+    if let Some(&(first, color)) = keys.first() {
+        if first > 0f64 {
+            keys.insert(0, (0f64, color));
+        }
+    }
+    if let Some(&(last, color)) = keys.last() {
+        if last < 1f64 {
+            keys.push((1f64, color));
+        }
+    }
+
+    // Pairs of keys closer than a hard edge are merged into a color change between two segments.
+    let mut segments: Vec<(f64, Color, f64, Color)> = Vec::new();
+    for pair in keys.windows(2) {
+        let ((left, left_color), (right, right_color)) = (pair[0], pair[1]);
+        match segments.last_mut() {
+            Some(segment) if right - left <= 2f64 * HARD_EDGE => segment.2 = right,
+            _ => segments.push((left, left_color, right, right_color)),
+        }
+    }
+
+    let blending = match palette.interpolation {
+        Interpolation::LINEAR => GGR_LINEAR,
+        Interpolation::CUBIC => GGR_SINE,
+        Interpolation::NEAREST => GGR_STEP,
+        Interpolation::EXPONENTIAL(_) => GGR_CURVED,
+    };
+    let mut ggr = String::from("GIMP Gradient\n");
+    if let Some(name) = &palette.name {
+        writeln!(ggr, "Name: {}", name).unwrap();
+    }
+    writeln!(ggr, "{}", segments.len()).unwrap();
+    for (left, left_color, right, right_color) in segments {
+        let coloring = if palette.space != ColorSpace::HSV {
+            GGR_RGB
+        } else {
+            let [left_hue, _, _] = ColorSpace::HSV.components(left_color);
+            let [right_hue, _, _] = ColorSpace::HSV.components(right_color);
+            if (right_hue - left_hue).rem_euclid(360f32) <= 180f32 { GGR_HSV_CCW } else { GGR_HSV_CW }
+        };
+        let [left_red, left_green, left_blue] = left_color.encode(Encoding::SRGB);
+        let [right_red, right_green, right_blue] = right_color.encode(Encoding::SRGB);
+        writeln!(
+            ggr,
+            "{:.6} {:.6} {:.6} {:.6} {:.6} {:.6} 1.000000 {:.6} {:.6} {:.6} 1.000000 {} {}",
+            left, (left + right) / 2f64, right,
+            left_red, left_green, left_blue,
+            right_red, right_green, right_blue,
+            blending, coloring,
+        ).unwrap();
+    }
+    ggr
+}
+
+/// Formats `palette` as Fractint map of 256 colors sampled evenly over the range spanning both
+/// the keys and `0.0..1.0`.
+pub fn to_map(palette: &PaletteDefinition) -> String {
+    let (start, end) = span(palette);
+    let mut map = String::new();
+    for index in 0..MAP_COLORS {
+        let position = start + (end - start) * index as f64 / MAP_COLORS as f64;
+        let color = palette.key_colors.color_at(position, palette.interpolation, palette.space);
+        let [red, green, blue] = color.encode(Encoding::SRGB).map(to_byte);
+        writeln!(map, "{} {} {}", red, green, blue).unwrap();
+    }
+    map
+}
+
+/// Range covering all key positions and `0.0..=1.0`.
+fn span(palette: &PaletteDefinition) -> (f64, f64) {
+    let (first, last) = palette.key_colors.range().unwrap_or((0f64, 1f64));
+    (first.min(0f64), last.max(1f64))
+}
+
+/// Appends a key, turning a key at the same position with a different color into a hard edge.
+///
+/// Returns `false` without appending if the position is before the last key.
+fn push_key(keys: &mut Vec<(f64, Color)>, position: f64, color: Color) -> bool {
+    match keys.last_mut() {
+        Some((last, _)) if *last > position => return false,
+        Some((last, last_color)) if *last == position => {
+            if *last_color != color {
+                *last = position - HARD_EDGE;
+                keys.push((position, color));
+            }
+        }
+        _ => keys.push((position, color)),
+    }
+    true
+}
+
+fn definition_error(error: impl Display) -> PaletteFileError {
+    PaletteFileError::Definition(error.to_string())
+}
+
+fn invalid_line(line: usize, content: &str) -> PaletteFileError {
+    PaletteFileError::InvalidLine { line, content: content.to_string() }
+}
+
+/// Error while reading or writing a palette file.
+#[derive(Debug)]
+pub enum PaletteFileError {
+    Io(io::Error),
+    /// The file extension is not one of the supported formats.
+    UnknownFormat(String),
+    /// A GIMP gradient does not start with `GIMP Gradient`.
+    MissingHeader,
+    /// The one-based line could not be parsed.
+    InvalidLine { line: usize, content: String },
+    /// A GIMP gradient declares a different number of segments than it contains.
+    SegmentCount { expected: usize, found: usize },
+    UnexpectedEnd,
+    /// The file contains no colors.
+    Empty,
+    /// A TOML, JSON or RON palette definition could not be read or written.
+    Definition(String),
+}
+
+impl From<io::Error> for PaletteFileError {
+    fn from(error: io::Error) -> Self {
+        PaletteFileError::Io(error)
+    }
+}
+
+impl Display for PaletteFileError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PaletteFileError::Io(error) => write!(f, "{}", error),
+            PaletteFileError::UnknownFormat(extension) => {
+                let expected = "`toml`, `json`, `ron`, `ggr`, `map` or `ugr`";
+                write!(f, "unknown palette format `{}`, expected {}", extension, expected)
+            }
+            PaletteFileError::MissingHeader => write!(f, "missing `GIMP Gradient` header"),
+            PaletteFileError::InvalidLine { line, content } => write!(f, "invalid line {}: `{}`", line, content),
+            PaletteFileError::SegmentCount { expected, found } => {
+                write!(f, "expected {} gradient segments, found {}", expected, found)
+            }
+            PaletteFileError::UnexpectedEnd => write!(f, "unexpected end of file"),
+            PaletteFileError::Empty => write!(f, "palette contains no colors"),
+            PaletteFileError::Definition(message) => write!(f, "invalid palette definition: {}", message),
+        }
+    }
+}
+
+impl Error for PaletteFileError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            PaletteFileError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: Color = Color::new(1f32, 0f32, 0f32);
+    const BLUE: Color = Color::new(0f32, 0f32, 1f32);
+
+    fn assert_keys(palette: &PaletteDefinition, expected: &[(f64, Color)]) {
+        let keys: Vec<(f64, Color)> = palette.key_colors.iter().collect();
+        assert_eq!(keys.len(), expected.len(), "{:?}", keys);
+        for ((position, color), (expected_position, expected_color)) in keys.iter().zip(expected) {
+            assert!((position - expected_position).abs() < 1e-6, "{:?}", keys);
+            let components = [color.red(), color.green(), color.blue()];
+            let expected = [expected_color.red(), expected_color.green(), expected_color.blue()];
+            for (component, expected) in components.iter().zip(expected) {
+                assert!((component - expected).abs() < 1e-5, "{:?}", keys);
+            }
+        }
+    }
+
+    #[test]
+    fn parses_ggr() {
+        let palette = parse_ggr(
+            "GIMP Gradient\n\
+             Name: Test\n\
+             2\n\
+             0.000000 0.300000 0.500000 0.000000 0.000000 0.000000 1.000000 1.000000 0.000000 0.000000 1.000000 0 0\n\
+             0.500000 0.750000 1.000000 1.000000 0.000000 0.000000 1.000000 0.000000 0.000000 1.000000 1.000000 1 0\n",
+        ).unwrap();
+
+        assert_eq!(palette.name.as_deref(), Some("Test"));
+        assert!(matches!(palette.interpolation, Interpolation::LINEAR));
+        assert_eq!(palette.space, ColorSpace::RGB);
+        // The off-center midpoint of the first segment becomes a key, the curved second segment does not.
+        let middle = Color::new(0.5f32, 0f32, 0f32);
+        assert_keys(&palette, &[(0f64, Color::BLACK), (0.3f64, middle), (0.5f64, RED), (1f64, BLUE)]);
+    }
+
+    #[test]
+    fn rejects_overlapping_ggr_segments() {
+        let contents = |second_left: &str| format!(
+            "GIMP Gradient\n\
+             2\n\
+             0.0 0.25 0.5 0 0 0 1 1 0 0 1 0 0\n\
+             {} 0.75 1.0 0 0 1 1 1 1 1 1 0 0\n",
+            second_left,
+        );
+        // Adjacent segments with different colors meet at a hard edge.
+        let palette = parse_ggr(&contents("0.5")).unwrap();
+        assert_keys(&palette, &[(0f64, Color::BLACK), (0.5f64, RED), (0.5f64, BLUE), (1f64, Color::WHITE)]);
+        assert!(matches!(parse_ggr(&contents("0.4")), Err(PaletteFileError::InvalidLine { line: 4, .. })));
+    }
+
+    #[test]
+    fn ggr_round_trips() {
+        let palette = PaletteDefinition {
+            name: Some("Round trip".to_string()),
+            interpolation: Interpolation::LINEAR,
+            space: ColorSpace::RGB,
+            key_colors: KeyColors::from([(0f64, Color::BLACK), (0.25f64, RED), (1f64, BLUE)]),
+        };
+        let parsed = parse_ggr(&to_ggr(&palette)).unwrap();
+        assert_eq!(parsed.name, palette.name);
+        assert_keys(&parsed, &palette.key_colors.iter().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn parses_map() {
+        let palette = parse_map("0 0 0 black\n; comment\n255 0 0\n0 0 255\n").unwrap();
+        assert_keys(&palette, &[(0f64, Color::BLACK), (1f64 / 3f64, RED), (2f64 / 3f64, BLUE), (1f64, Color::BLACK)]);
+    }
+
+    #[test]
+    fn parses_ugr() {
+        let palettes = parse_ugr(
+            "fire {\n\
+             gradient:\n\
+               title=\"Fire\" smooth=no\n\
+               index=100 color=255\n\
+               index=-100 color=16711680\n\
+             opacity:\n\
+               index=0 opacity=255\n\
+             }\n",
+        ).unwrap();
+
+        assert_eq!(palettes.len(), 1);
+        let palette = &palettes[0];
+        assert_eq!(palette.name.as_deref(), Some("Fire"));
+        assert!(matches!(palette.interpolation, Interpolation::LINEAR));
+        // Index -100 wraps to 300, and the color halfway from it to index 100 is repeated at both ends.
+        let wrap = Color::new(0.5f32, 0f32, 0.5f32);
+        assert_keys(palette, &[(0f64, wrap), (0.25f64, RED), (0.75f64, BLUE), (1f64, wrap)]);
+    }
+
+    #[test]
+    fn reports_invalid_lines() {
+        assert!(matches!(parse_map("0 0\n"), Err(PaletteFileError::InvalidLine { line: 1, .. })));
+        assert!(matches!(parse_ggr("GIMP Palette\n"), Err(PaletteFileError::MissingHeader)));
+    }
+}
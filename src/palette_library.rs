@@ -0,0 +1,168 @@
+use crate::color_palette::PaletteDefinition;
+use crate::color_space::ColorSpace;
+use crate::interpolatable::Interpolation;
+use crate::palette_file::{load_palettes, PaletteFileError};
+
+/// Palette shipped with the application, with sRGB encoded key colors.
+struct BuiltinPalette {
+    name: &'static str,
+    interpolation: Interpolation,
+    space: ColorSpace,
+    keys: &'static [(f64, &'static str)],
+}
+
+const BUILTIN_PALETTES: [BuiltinPalette; 6] = [
+    BuiltinPalette {
+        name: "ultra-fractal",
+        interpolation: Interpolation::CUBIC,
+        space: ColorSpace::OKLAB,
+        keys: &[
+            (0.0, "#000764"),
+            (0.16, "#206bcb"),
+            (0.42, "#edffff"),
+            (0.6425, "#ffaa00"),
+            (0.8575, "#000200"),
+            (1.0, "#000764"),
+        ],
+    },
+    // The matplotlib colormaps are sampled at nine evenly spaced keys and interpolated in Oklab,
+    // which keeps them close to perceptually uniform.
+    BuiltinPalette {
+        name: "viridis",
+        interpolation: Interpolation::LINEAR,
+        space: ColorSpace::OKLAB,
+        keys: &[
+            (0.0, "#440154"),
+            (0.125, "#472d7b"),
+            (0.25, "#3b528b"),
+            (0.375, "#2c728e"),
+            (0.5, "#21908c"),
+            (0.625, "#27ad81"),
+            (0.75, "#5dc863"),
+            (0.875, "#aadc32"),
+            (1.0, "#fde725"),
+        ],
+    },
+    BuiltinPalette {
+        name: "magma",
+        interpolation: Interpolation::LINEAR,
+        space: ColorSpace::OKLAB,
+        keys: &[
+            (0.0, "#000004"),
+            (0.125, "#1d1147"),
+            (0.25, "#51127c"),
+            (0.375, "#822681"),
+            (0.5, "#b63679"),
+            (0.625, "#e65164"),
+            (0.75, "#fb8861"),
+            (0.875, "#fec287"),
+            (1.0, "#fcfdbf"),
+        ],
+    },
+    BuiltinPalette {
+        name: "plasma",
+        interpolation: Interpolation::LINEAR,
+        space: ColorSpace::OKLAB,
+        keys: &[
+            (0.0, "#0d0887"),
+            (0.125, "#4c02a1"),
+            (0.25, "#7e03a8"),
+            (0.375, "#a92395"),
+            (0.5, "#cc4778"),
+            (0.625, "#e56b5d"),
+            (0.75, "#f89441"),
+            (0.875, "#fdc328"),
+            (1.0, "#f0f921"),
+        ],
+    },
+    BuiltinPalette {
+        name: "twilight",
+        interpolation: Interpolation::LINEAR,
+        space: ColorSpace::OKLAB,
+        keys: &[
+            (0.0, "#e2d9e2"),
+            (0.125, "#a5b8cd"),
+            (0.25, "#6a8ac4"),
+            (0.375, "#5e4ba9"),
+            (0.5, "#301437"),
+            (0.625, "#6c1f53"),
+            (0.75, "#b05b4b"),
+            (0.875, "#d09f8c"),
+            (1.0, "#e2d9e2"),
+        ],
+    },
+    BuiltinPalette {
+        name: "grayscale",
+        interpolation: Interpolation::LINEAR,
+        space: ColorSpace::OKLAB,
+        keys: &[(0.0, "black"), (1.0, "white")],
+    },
+];
+
+/// Names of all built-in palettes.
+pub fn builtin_names() -> impl Iterator<Item=&'static str> {
+    BUILTIN_PALETTES.iter().map(|palette| palette.name)
+}
+
+/// Built-in palette called `name`, ignoring case.
+///
+/// `ultra-fractal` and `twilight` are cyclic, so they fit a
+/// [RepeatingColorPalette](crate::color_palette::RepeatingColorPalette).
+pub fn builtin_palette(name: &str) -> Option<PaletteDefinition> {
+    let palette = BUILTIN_PALETTES.iter().find(|palette| palette.name.eq_ignore_ascii_case(name))?;
+    let key_colors = palette.keys.iter()
+        .map(|(position, color)| (*position, color.parse().expect("built-in palette colors are valid")))
+        .collect();
+    Some(PaletteDefinition {
+        name: Some(palette.name.to_string()),
+        interpolation: palette.interpolation,
+        space: palette.space,
+        key_colors,
+    })
+}
+
+/// Built-in palette called `name`, or else the first palette in the file at path `name`.
+pub fn find_palette(name: &str) -> Result<PaletteDefinition, PaletteFileError> {
+    if let Some(palette) = builtin_palette(name) {
+        return Ok(palette);
+    }
+    load_palettes(name)?.into_iter().next().ok_or(PaletteFileError::Empty)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn builtin_palettes_parse() {
+        for name in builtin_names() {
+            let palette = builtin_palette(name).unwrap();
+            assert_eq!(palette.name.as_deref(), Some(name));
+            let positions: Vec<f64> = palette.key_colors.iter().map(|(position, _)| position).collect();
+            assert_eq!(positions.first(), Some(&0.0), "{}", name);
+            assert_eq!(positions.last(), Some(&1.0), "{}", name);
+        }
+        assert_eq!(builtin_names().count(), BUILTIN_PALETTES.len());
+    }
+
+    #[test]
+    fn lookup_ignores_case() {
+        assert_eq!(builtin_palette("Viridis").unwrap().name.as_deref(), Some("viridis"));
+        assert_eq!(find_palette("ULTRA-FRACTAL").unwrap().name.as_deref(), Some("ultra-fractal"));
+        assert!(builtin_palette("inferno").is_none());
+    }
+
+    #[test]
+    fn unknown_names_are_loaded_from_files() {
+        let path = std::env::temp_dir().join(format!("palette_library_{}.map", std::process::id()));
+        fs::write(&path, "255 0 0\n0 0 255\n").unwrap();
+        let palette = find_palette(path.to_str().unwrap());
+        fs::remove_file(&path).unwrap();
+        assert_eq!(palette.unwrap().key_colors.iter().count(), 3);
+
+        assert!(matches!(find_palette("missing.map"), Err(PaletteFileError::Io(_))));
+        assert!(matches!(find_palette("inferno"), Err(PaletteFileError::UnknownFormat(_))));
+    }
+}
@@ -1,15 +1,82 @@
+use crate::complex::{Complex, ComplexF64, Real};
+
+#[derive(Clone, Copy)]
 pub struct RenderingSettings {
     pub resolution: Resolution,
     pub sampling: MultiSampling,
 }
 
+#[derive(Clone, Copy)]
 pub struct Resolution {
-    width: u32,
-    height: u32,
+    pub width: u32,
+    pub height: u32,
 }
 
+#[derive(Clone, Copy)]
 pub enum MultiSampling {
     NONE,
     X2,
     X4,
-}
\ No newline at end of file
+}
+
+impl MultiSampling {
+    /// Samples per pixel along each axis.
+    pub fn factor(self) -> u32 {
+        match self {
+            MultiSampling::NONE => 1,
+            MultiSampling::X2 => 2,
+            MultiSampling::X4 => 4,
+        }
+    }
+}
+
+impl RenderingSettings {
+    pub fn sample_width(&self) -> u32 {
+        self.resolution.width * self.sampling.factor()
+    }
+
+    pub fn sample_height(&self) -> u32 {
+        self.resolution.height * self.sampling.factor()
+    }
+
+    pub fn sample_count(&self) -> usize {
+        self.sample_width() as usize * self.sample_height() as usize
+    }
+
+    /// Distance on the complex plane between two neighbouring samples at `zoom`.
+    pub fn sample_step<T: Real>(&self, zoom: T) -> T {
+        let width = self.sample_width();
+        let height = self.sample_height();
+
+        if width as f32 * 1.5 > height as f32 {
+            T::from_f64(2f64) / (zoom * T::from_f64(height as f64))
+        } else {
+            T::from_f64(3f64) / (zoom * T::from_f64(width as f64))
+        }
+    }
+
+    /// Location on the complex plane of the sample at `x`, `y` when viewing `center` at `zoom`.
+    pub fn sample_location<T: Real>(&self, center: Complex<T>, zoom: T, x: u32, y: u32) -> Complex<T> {
+        let pixel_step = self.sample_step(zoom);
+        let half_width = T::from_f64(self.sample_width() as f64 / 2f64);
+        let half_height = T::from_f64(self.sample_height() as f64 / 2f64);
+        let re = pixel_step * (T::from_f64(x as f64) - half_width) + center.re;
+        let im = pixel_step * (half_height - T::from_f64(y as f64)) + center.im;
+        Complex::new(re, im)
+    }
+
+    /// Row-major index of the sample containing `location`, if it lies inside the view.
+    ///
+    /// Inverse of [RenderingSettings::sample_location], calculated in `f64` precision.
+    pub fn sample_index(&self, center: ComplexF64, zoom: f64, location: ComplexF64) -> Option<usize> {
+        let pixel_step = self.sample_step(zoom);
+        let x = ((location.re - center.re) / pixel_step + self.sample_width() as f64 / 2f64).round();
+        let y = (self.sample_height() as f64 / 2f64 - (location.im - center.im) / pixel_step).round();
+
+        if x < 0f64 || y < 0f64 || x >= self.sample_width() as f64 || y >= self.sample_height() as f64 {
+            None
+        } else {
+            Some(y as usize * self.sample_width() as usize + x as usize)
+        }
+    }
+}
@@ -0,0 +1,103 @@
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use rayon::prelude::*;
+
+/// Samples handled by a single random number stream.
+const CHUNK_SAMPLES: u64 = 1 << 16;
+/// Accumulators filled in parallel at most, each with every lane-th chunk in order.
+///
+/// Fixed instead of one per thread, so the result does not depend on the number of threads,
+/// while memory stays bounded by the lanes instead of growing with the chunks.
+const LANES: u64 = 8;
+/// Memory all lanes together may use for their accumulators, fewer lanes are used for large
+/// accumulators. Always at least one, even if its accumulator alone is larger.
+const LANE_MEMORY: usize = 1 << 30;
+
+/// Number of lanes for accumulators of `accumulator_bytes`, which only depends on their size.
+fn lanes(accumulator_bytes: usize) -> u64 {
+    (LANE_MEMORY / accumulator_bytes.max(1)).clamp(1, LANES as usize) as u64
+}
+
+/// Splits `samples` into chunks with their own random stream derived from `seed` and accumulates
+/// them in parallel lanes, which are combined in lane order. The number of lanes is limited by
+/// [LANE_MEMORY] given the size of an accumulator in `accumulator_bytes`.
+///
+/// Each lane keeps one accumulator from `initial` and one state from `initial_state` over all of
+/// its chunks, e.g. a Markov chain which would otherwise restart for every chunk.
+///
+/// The result only depends on `seed`, not on the number of threads.
+pub(crate) fn accumulate_parallel<A: Send, S>(
+    samples: u64,
+    seed: u64,
+    accumulator_bytes: usize,
+    initial: impl Fn() -> A + Sync,
+    initial_state: impl Fn() -> S + Sync,
+    accumulate: impl Fn(&mut A, &mut S, &mut ChaCha8Rng, u64) + Sync,
+    mut combine: impl FnMut(&mut A, A),
+) -> A {
+    let chunks = samples.div_ceil(CHUNK_SAMPLES);
+    let lane_count = lanes(accumulator_bytes);
+    let lanes: Vec<A> = (0..lane_count.min(chunks).max(1))
+        .into_par_iter()
+        .map(|lane| {
+            let mut accumulator = initial();
+            let mut state = initial_state();
+            for chunk in (lane..chunks).step_by(lane_count as usize) {
+                let mut random = ChaCha8Rng::seed_from_u64(seed);
+                random.set_stream(chunk);
+                let chunk_samples = CHUNK_SAMPLES.min(samples - chunk * CHUNK_SAMPLES);
+                accumulate(&mut accumulator, &mut state, &mut random, chunk_samples);
+            }
+            accumulator
+        })
+        .collect();
+
+    let mut lanes = lanes.into_iter();
+    let mut result = lanes.next().unwrap_or_else(initial);
+    for lane in lanes {
+        combine(&mut result, lane);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+
+    use super::*;
+
+    #[test]
+    fn lanes_are_limited_by_memory() {
+        assert_eq!(lanes(0), LANES);
+        assert_eq!(lanes(LANE_MEMORY / LANES as usize), LANES);
+        assert_eq!(lanes(LANE_MEMORY / 3), 3);
+        assert_eq!(lanes(LANE_MEMORY), 1);
+        // A 4K image with 4x4 multisampling and three f64 channels.
+        assert_eq!(lanes(3840 * 4 * 2160 * 4 * 3 * 8), 1);
+    }
+
+    #[test]
+    fn result_does_not_depend_on_threads() {
+        let sum = |threads: usize| {
+            let pool = rayon::ThreadPoolBuilder::new().num_threads(threads).build().unwrap();
+            pool.install(|| {
+                accumulate_parallel(
+                    20 * CHUNK_SAMPLES + 7,
+                    42,
+                    8,
+                    || 0f64,
+                    || 0u64,
+                    |sum, count, random, samples| {
+                        for _ in 0..samples {
+                            *sum += random.gen::<f64>() / (1f64 + *count as f64);
+                            *count += 1;
+                        }
+                    },
+                    |sum, lane| *sum += lane,
+                )
+            })
+        };
+        assert_eq!(sum(1), sum(3));
+        assert_eq!(sum(1), sum(8));
+    }
+}